@@ -0,0 +1,8 @@
+mod engine_settings;
+mod mistake_analysis;
+mod uci_engine;
+mod uci_messages;
+pub use engine_settings::*;
+pub use mistake_analysis::*;
+pub use uci_engine::*;
+pub use uci_messages::*;
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use super::uci_engine::{EngineError, UciEngine};
+use super::uci_messages::SearchLimit;
+
+const ELO_LIMITED_MOVE_TIME_MS: u32 = 1000;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum StrengthLimit {
+    Elo(u32),
+    Depth(u32),
+    MoveTime(u32),
+}
+
+impl StrengthLimit {
+    pub fn value(self) -> u32 {
+        match self {
+            StrengthLimit::Elo(value)
+            | StrengthLimit::Depth(value)
+            | StrengthLimit::MoveTime(value) => value,
+        }
+    }
+
+    pub fn configure(self, engine: &mut UciEngine) -> Result<(), EngineError> {
+        match self {
+            StrengthLimit::Elo(elo) => {
+                engine.set_option("UCI_LimitStrength", "true")?;
+                engine.set_option("UCI_Elo", &elo.to_string())
+            }
+            _ => engine.set_option("UCI_LimitStrength", "false"),
+        }
+    }
+
+    pub fn search_limit(self) -> SearchLimit {
+        match self {
+            StrengthLimit::Elo(_) => SearchLimit::MoveTime(ELO_LIMITED_MOVE_TIME_MS),
+            StrengthLimit::Depth(depth) => SearchLimit::Depth(depth),
+            StrengthLimit::MoveTime(time_ms) => SearchLimit::MoveTime(time_ms),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct EngineSettings {
+    pub path: Option<PathBuf>,
+    pub strength: StrengthLimit,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        EngineSettings {
+            path: None,
+            strength: StrengthLimit::Elo(1500),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use shakmaty::san::SanPlus;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Color, Position, Setup};
+
+use super::uci_messages::{EngineInfo, Score, ScoreBound};
+use crate::pgn::{position_from_fen, AnnotationColor, GraphicalAnnotations};
+
+const MATE_CENTIPAWNS: i32 = 100_000;
+const REFUTATION_DISPLAYED_PLIES: usize = 6;
+
+#[derive(Debug, Clone)]
+pub struct LineEvaluation {
+    // From the point of view of the side who played the move: a non negative mate
+    // means that this side mates.
+    pub score: Score,
+    pub pv: Vec<Uci>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum AnalysisStage {
+    PlayedMove,
+    ExpectedMove,
+    Done,
+}
+
+pub struct MistakeAnalysis {
+    fen: String,
+    position: Chess,
+    played_move: Uci,
+    expected_move: Uci,
+    stage: AnalysisStage,
+    played_evaluation: Option<LineEvaluation>,
+    expected_evaluation: Option<LineEvaluation>,
+}
+
+impl MistakeAnalysis {
+    pub fn new(fen: &str, played_uci: &str, expected_uci: &str) -> Option<Self> {
+        Some(MistakeAnalysis {
+            fen: fen.to_string(),
+            position: position_from_fen(fen)?,
+            played_move: Uci::from_ascii(played_uci.as_bytes()).ok()?,
+            expected_move: Uci::from_ascii(expected_uci.as_bytes()).ok()?,
+            stage: AnalysisStage::PlayedMove,
+            played_evaluation: None,
+            expected_evaluation: None,
+        })
+    }
+
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    /// The move to search after, from the analysed position, or None once both are done.
+    pub fn move_to_search(&self) -> Option<&Uci> {
+        match self.stage {
+            AnalysisStage::PlayedMove => Some(&self.played_move),
+            AnalysisStage::ExpectedMove => Some(&self.expected_move),
+            AnalysisStage::Done => None,
+        }
+    }
+
+    pub fn record_info(&mut self, info: &EngineInfo) {
+        if info.multipv.map_or(false, |multipv| multipv != 1) {
+            return;
+        }
+        // Bounds only tell that the search is still going on.
+        let score = match info.score {
+            // The side to move is already mated.
+            Some((Score::Mate(0), ScoreBound::Exact)) => Score::Mate(0),
+            Some((score, ScoreBound::Exact)) => score.negated(),
+            _ => return,
+        };
+
+        let evaluation = Some(LineEvaluation {
+            score,
+            pv: info.pv.clone(),
+        });
+        match self.stage {
+            AnalysisStage::PlayedMove => self.played_evaluation = evaluation,
+            AnalysisStage::ExpectedMove => self.expected_evaluation = evaluation,
+            AnalysisStage::Done => {}
+        }
+    }
+
+    pub fn search_finished(&mut self) {
+        self.stage = match self.stage {
+            AnalysisStage::PlayedMove => AnalysisStage::ExpectedMove,
+            _ => AnalysisStage::Done,
+        };
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.stage == AnalysisStage::Done
+    }
+
+    pub fn centipawns_lost(&self) -> Option<i32> {
+        let played = self.played_evaluation.as_ref()?;
+        let expected = self.expected_evaluation.as_ref()?;
+
+        Some(score_centipawns(expected.score) - score_centipawns(played.score))
+    }
+
+    pub fn description(&self) -> String {
+        let played_san = self.san_of(&self.played_move);
+        let expected_san = self.san_of(&self.expected_move);
+        let mut lines = vec![];
+
+        match (&self.played_evaluation, &self.expected_evaluation) {
+            (Some(played), Some(expected)) => {
+                lines.push(format!(
+                    "Your move {} is evaluated {}, the expected {} is evaluated {}.",
+                    played_san,
+                    score_description(played.score),
+                    expected_san,
+                    score_description(expected.score)
+                ));
+                if let Some(lost) = self.centipawns_lost() {
+                    if lost > 0 {
+                        lines.push(format!("You lose about {:.2} pawns.", lost as f64 / 100.0));
+                    }
+                }
+            }
+            _ => lines.push(format!(
+                "Could not evaluate {} against the expected {}.",
+                played_san, expected_san
+            )),
+        }
+
+        if let Some(refutation) = self.refutation_text() {
+            lines.push(format!("Refutation: {}", refutation));
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn annotations(&self) -> GraphicalAnnotations {
+        let mut annotations = GraphicalAnnotations::default();
+        add_arrow(&mut annotations, &self.played_move, AnnotationColor::Red);
+        add_arrow(
+            &mut annotations,
+            &self.expected_move,
+            AnnotationColor::Green,
+        );
+
+        let refutation_reply = self
+            .played_evaluation
+            .as_ref()
+            .and_then(|evaluation| evaluation.pv.first());
+        if let Some(reply) = refutation_reply {
+            add_arrow(&mut annotations, reply, AnnotationColor::Yellow);
+        }
+
+        annotations
+    }
+
+    fn san_of(&self, uci: &Uci) -> String {
+        uci.to_move(&self.position)
+            .map(|legal_move| SanPlus::from_move(self.position.clone(), &legal_move).to_string())
+            .unwrap_or_else(|_| uci.to_string())
+    }
+
+    fn refutation_text(&self) -> Option<String> {
+        let evaluation = self.played_evaluation.as_ref()?;
+        let mut position = self.position.clone();
+        let played_move = self.played_move.to_move(&position).ok()?;
+        position.play_unchecked(&played_move);
+
+        let mut moves = vec![];
+        for (ply, uci) in evaluation
+            .pv
+            .iter()
+            .take(REFUTATION_DISPLAYED_PLIES)
+            .enumerate()
+        {
+            let legal_move = match uci.to_move(&position) {
+                Ok(legal_move) => legal_move,
+                Err(_) => break,
+            };
+            let san = SanPlus::from_move(position.clone(), &legal_move).to_string();
+            moves.push(match position.turn() {
+                Color::White => format!("{}. {}", position.fullmoves(), san),
+                Color::Black if ply == 0 => format!("{}... {}", position.fullmoves(), san),
+                Color::Black => san,
+            });
+            position.play_unchecked(&legal_move);
+        }
+
+        if moves.is_empty() {
+            None
+        } else {
+            Some(moves.join(" "))
+        }
+    }
+}
+
+fn add_arrow(annotations: &mut GraphicalAnnotations, uci: &Uci, color: AnnotationColor) {
+    if let Uci::Normal { from, to, .. } = uci {
+        annotations.toggle_arrow(*from, *to, color);
+    }
+}
+
+fn score_centipawns(score: Score) -> i32 {
+    match score {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) if moves >= 0 => MATE_CENTIPAWNS - moves,
+        Score::Mate(moves) => -MATE_CENTIPAWNS - moves,
+    }
+}
+
+fn score_description(score: Score) -> String {
+    match score {
+        Score::Centipawns(centipawns) => format!("{:+.2}", centipawns as f64 / 100.0),
+        Score::Mate(0) => "checkmate".to_string(),
+        Score::Mate(moves) if moves > 0 => format!("mate in {}", moves),
+        Score::Mate(moves) => format!("mated in {}", -moves),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn info(score: Score, bound: ScoreBound, pv: &[&str]) -> EngineInfo {
+        EngineInfo {
+            score: Some((score, bound)),
+            pv: pv
+                .iter()
+                .map(|uci| Uci::from_ascii(uci.as_bytes()).unwrap())
+                .collect(),
+            ..EngineInfo::default()
+        }
+    }
+
+    // The engine answers after each move, for the opponent then to move.
+    fn analysis(played_info: EngineInfo, expected_info: EngineInfo) -> MistakeAnalysis {
+        let mut analysis = MistakeAnalysis::new(START, "f2f3", "e2e4").unwrap();
+        analysis.record_info(&played_info);
+        analysis.search_finished();
+        analysis.record_info(&expected_info);
+        analysis.search_finished();
+        analysis
+    }
+
+    fn played_score(analysis: &MistakeAnalysis) -> Option<Score> {
+        analysis
+            .played_evaluation
+            .as_ref()
+            .map(|evaluation| evaluation.score)
+    }
+
+    #[test]
+    fn scores_are_seen_by_the_side_who_played() {
+        let analysis = analysis(
+            info(Score::Centipawns(60), ScoreBound::Exact, &[]),
+            info(Score::Centipawns(-30), ScoreBound::Exact, &[]),
+        );
+
+        assert!(analysis.is_complete());
+        assert_eq!(played_score(&analysis), Some(Score::Centipawns(-60)));
+        assert_eq!(analysis.centipawns_lost(), Some(90));
+    }
+
+    #[test]
+    fn bound_scores_are_ignored() {
+        let mut analysis = MistakeAnalysis::new(START, "f2f3", "e2e4").unwrap();
+        analysis.record_info(&info(Score::Centipawns(60), ScoreBound::Exact, &[]));
+        analysis.record_info(&info(Score::Centipawns(400), ScoreBound::Lower, &[]));
+        analysis.record_info(&info(Score::Centipawns(-200), ScoreBound::Upper, &[]));
+
+        assert_eq!(played_score(&analysis), Some(Score::Centipawns(-60)));
+    }
+
+    #[test]
+    fn secondary_lines_are_ignored() {
+        let mut analysis = MistakeAnalysis::new(START, "f2f3", "e2e4").unwrap();
+        analysis.record_info(&EngineInfo {
+            multipv: Some(2),
+            ..info(Score::Centipawns(60), ScoreBound::Exact, &[])
+        });
+
+        assert_eq!(played_score(&analysis), None);
+    }
+
+    #[test]
+    fn mates_weigh_more_than_any_material() {
+        let analysis = analysis(
+            info(Score::Mate(2), ScoreBound::Exact, &[]),
+            info(Score::Centipawns(-30), ScoreBound::Exact, &[]),
+        );
+
+        assert_eq!(played_score(&analysis), Some(Score::Mate(-2)));
+        assert!(analysis.centipawns_lost().unwrap() > 90_000);
+
+        assert!(score_centipawns(Score::Mate(0)) > score_centipawns(Score::Mate(1)));
+        assert!(score_centipawns(Score::Mate(1)) > score_centipawns(Score::Mate(3)));
+        assert!(score_centipawns(Score::Mate(3)) > score_centipawns(Score::Centipawns(5000)));
+        assert!(score_centipawns(Score::Centipawns(-5000)) > score_centipawns(Score::Mate(-3)));
+        assert!(score_centipawns(Score::Mate(-3)) > score_centipawns(Score::Mate(-1)));
+    }
+
+    #[test]
+    fn describes_the_mistake_and_its_refutation() {
+        let analysis = analysis(
+            info(
+                Score::Centipawns(60),
+                ScoreBound::Exact,
+                &["e7e5", "g2g4", "d8h4"],
+            ),
+            info(Score::Centipawns(-30), ScoreBound::Exact, &[]),
+        );
+
+        assert_eq!(
+            analysis.description(),
+            "Your move f3 is evaluated -0.60, the expected e4 is evaluated +0.30.\n\
+             You lose about 0.90 pawns.\n\
+             Refutation: 1... e5 2. g4 Qh4#"
+        );
+    }
+
+    #[test]
+    fn describes_mate_scores() {
+        assert_eq!(score_description(Score::Mate(0)), "checkmate");
+        assert_eq!(score_description(Score::Mate(2)), "mate in 2");
+        assert_eq!(score_description(Score::Mate(-3)), "mated in 3");
+    }
+}
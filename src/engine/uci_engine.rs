@@ -0,0 +1,287 @@
+use failure::Fail;
+use shakmaty::uci::Uci;
+
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::uci_messages::*;
+
+const QUIT_GRACE_CHECKS: usize = 10;
+const QUIT_GRACE_CHECK_INTERVAL_MS: u64 = 20;
+
+#[derive(Debug, Fail)]
+pub enum EngineError {
+    #[fail(display = "Failed to launch engine {}: {}", path, cause)]
+    LaunchFailed { path: String, cause: String },
+    #[fail(display = "Failed to send command to engine: {}", cause)]
+    CommandFailed { cause: String },
+}
+
+pub struct UciEngine {
+    process: Child,
+    input: ChildStdin,
+    reader_thread: Option<JoinHandle<()>>,
+}
+
+impl UciEngine {
+    /// Launches the engine executable and forwards every recognized line of its output
+    /// to `on_event`, from a dedicated reader thread.
+    pub fn spawn<P, F>(path: P, mut on_event: F) -> Result<UciEngine, EngineError>
+    where
+        P: AsRef<OsStr>,
+        F: FnMut(EngineEvent) + Send + 'static,
+    {
+        let path = path.as_ref();
+        let launch_error = |cause: String| EngineError::LaunchFailed {
+            path: path.to_string_lossy().into_owned(),
+            cause,
+        };
+
+        let mut process = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| launch_error(err.to_string()))?;
+        let input = process
+            .stdin
+            .take()
+            .ok_or_else(|| launch_error("no standard input".to_string()))?;
+        let output = process
+            .stdout
+            .take()
+            .ok_or_else(|| launch_error("no standard output".to_string()))?;
+
+        let reader_thread = thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(_) => break,
+                };
+                if let Some(event) = parse_engine_line(&line) {
+                    on_event(event);
+                }
+            }
+            on_event(EngineEvent::Terminated);
+        });
+
+        let mut engine = UciEngine {
+            process,
+            input,
+            reader_thread: Some(reader_thread),
+        };
+        engine.send("uci")?;
+        Ok(engine)
+    }
+
+    /// Same as `spawn`, but events are delivered to the relm event loop through a channel.
+    pub fn spawn_with_sender<P>(
+        path: P,
+        sender: relm::Sender<EngineEvent>,
+    ) -> Result<UciEngine, EngineError>
+    where
+        P: AsRef<OsStr>,
+    {
+        UciEngine::spawn(path, move |event| {
+            let _ = sender.send(event);
+        })
+    }
+
+    pub fn send(&mut self, command: &str) -> Result<(), EngineError> {
+        writeln!(self.input, "{}", command)
+            .and_then(|_| self.input.flush())
+            .map_err(|err| EngineError::CommandFailed {
+                cause: err.to_string(),
+            })
+    }
+
+    pub fn is_ready(&mut self) -> Result<(), EngineError> {
+        self.send("isready")
+    }
+
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        self.send("ucinewgame")
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), EngineError> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn set_position(&mut self, fen: &str, moves: &[Uci]) -> Result<(), EngineError> {
+        self.send(&position_command(fen, moves))
+    }
+
+    pub fn go(&mut self, limit: SearchLimit) -> Result<(), EngineError> {
+        self.send(&limit.to_go_command())
+    }
+
+    pub fn stop(&mut self) -> Result<(), EngineError> {
+        self.send("stop")
+    }
+
+    pub fn quit(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let _ = self.send("quit");
+        let exited = (0..QUIT_GRACE_CHECKS).any(|_| {
+            let exited = self.process.try_wait().ok().flatten().is_some();
+            if !exited {
+                thread::sleep(Duration::from_millis(QUIT_GRACE_CHECK_INTERVAL_MS));
+            }
+            exited
+        });
+        if !exited {
+            let _ = self.process.kill();
+        }
+        let _ = self.process.wait();
+        if let Some(reader_thread) = self.reader_thread.take() {
+            let _ = reader_thread.join();
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        if self.reader_thread.is_some() {
+            self.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc::{channel, Receiver};
+
+    const FAKE_ENGINE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/fake_uci_engine.sh"
+    );
+    const EVENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn uci(text: &str) -> Uci {
+        Uci::from_ascii(text.as_bytes()).unwrap()
+    }
+
+    fn events_until<F>(events: &Receiver<EngineEvent>, is_last: F) -> Vec<EngineEvent>
+    where
+        F: Fn(&EngineEvent) -> bool,
+    {
+        let mut received = vec![];
+        loop {
+            let event = events
+                .recv_timeout(EVENT_TIMEOUT)
+                .expect("The fake engine stopped answering");
+            let done = is_last(&event);
+            received.push(event);
+            if done {
+                return received;
+            }
+        }
+    }
+
+    // The fake engine echoes every command as "info string received <command>".
+    fn received_commands(events: &[EngineEvent]) -> Vec<String> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::Info(EngineInfo {
+                    string: Some(text), ..
+                }) if text.starts_with("received ") => Some(text["received ".len()..].to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn infos(events: &[EngineEvent]) -> Vec<EngineInfo> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                EngineEvent::Info(info) if info.string.is_none() => Some(info.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn talks_uci_with_a_scripted_engine() {
+        let (sender, events) = channel();
+        let mut engine = UciEngine::spawn(FAKE_ENGINE, move |event| {
+            let _ = sender.send(event);
+        })
+        .expect("Could not launch the fake engine");
+
+        let handshake = events_until(&events, |event| *event == EngineEvent::UciOk);
+        assert_eq!(received_commands(&handshake), vec!["uci"]);
+        assert!(handshake.contains(&EngineEvent::Id {
+            name: "name".to_string(),
+            value: "Fake Engine".to_string(),
+        }));
+        assert!(handshake.contains(&EngineEvent::OptionDeclared(
+            "name Skill Level type spin default 20 min 0 max 20".to_string()
+        )));
+
+        engine.is_ready().unwrap();
+        let readiness = events_until(&events, |event| *event == EngineEvent::ReadyOk);
+        assert_eq!(received_commands(&readiness), vec!["isready"]);
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        engine.new_game().unwrap();
+        engine.set_position(start, &[uci("e2e4")]).unwrap();
+        engine.go(SearchLimit::Depth(12)).unwrap();
+        let search = events_until(&events, |event| match event {
+            EngineEvent::BestMove(_) => true,
+            _ => false,
+        });
+        assert_eq!(
+            received_commands(&search),
+            vec![
+                "ucinewgame".to_string(),
+                format!("position fen {} moves e2e4", start),
+                "go depth 12".to_string(),
+            ]
+        );
+
+        let infos = infos(&search);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].depth, Some(12));
+        assert_eq!(infos[0].seldepth, Some(18));
+        assert_eq!(infos[0].multipv, Some(1));
+        assert_eq!(
+            infos[0].score,
+            Some((Score::Centipawns(34), ScoreBound::Exact))
+        );
+        assert_eq!(infos[0].pv, vec![uci("e2e4"), uci("e7e5"), uci("g1f3")]);
+        assert_eq!(infos[1].multipv, Some(2));
+        assert_eq!(infos[1].score, Some((Score::Mate(-3), ScoreBound::Exact)));
+        assert_eq!(infos[1].pv, vec![uci("d2d4"), uci("d7d5")]);
+        assert_eq!(
+            search.last(),
+            Some(&EngineEvent::BestMove(BestMove {
+                best_move: Some(uci("e2e4")),
+                ponder: Some(uci("e7e5")),
+            }))
+        );
+
+        engine.quit();
+        let shutdown = events_until(&events, |event| *event == EngineEvent::Terminated);
+        assert_eq!(received_commands(&shutdown), vec!["quit"]);
+    }
+
+    #[test]
+    fn reports_a_missing_executable() {
+        let result = UciEngine::spawn("/nonexistent/uci-engine", |_| {});
+        match result {
+            Err(EngineError::LaunchFailed { path, .. }) => {
+                assert_eq!(path, "/nonexistent/uci-engine")
+            }
+            _ => panic!("Launching a missing executable should fail"),
+        }
+    }
+}
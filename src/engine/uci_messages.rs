@@ -0,0 +1,305 @@
+use shakmaty::uci::Uci;
+use shakmaty::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+impl Score {
+    pub fn negated(self) -> Score {
+        match self {
+            Score::Centipawns(value) => Score::Centipawns(-value),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    }
+
+    /// Engines report scores from the side to move point of view.
+    pub fn to_white_point_of_view(self, side_to_move: Color) -> Score {
+        match side_to_move {
+            Color::White => self,
+            Color::Black => self.negated(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreBound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<(Score, ScoreBound)>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time_ms: Option<u64>,
+    pub pv: Vec<Uci>,
+    pub string: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestMove {
+    pub best_move: Option<Uci>,
+    pub ponder: Option<Uci>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineEvent {
+    Id { name: String, value: String },
+    OptionDeclared(String),
+    UciOk,
+    ReadyOk,
+    Info(EngineInfo),
+    BestMove(BestMove),
+    Terminated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchLimit {
+    Depth(u32),
+    MoveTime(u32),
+    Nodes(u64),
+    Infinite,
+}
+
+impl SearchLimit {
+    pub fn to_go_command(self) -> String {
+        match self {
+            SearchLimit::Depth(depth) => format!("go depth {}", depth),
+            SearchLimit::MoveTime(time_ms) => format!("go movetime {}", time_ms),
+            SearchLimit::Nodes(nodes) => format!("go nodes {}", nodes),
+            SearchLimit::Infinite => "go infinite".to_string(),
+        }
+    }
+}
+
+pub fn position_command(fen: &str, moves: &[Uci]) -> String {
+    let mut command = format!("position fen {}", fen);
+    if !moves.is_empty() {
+        command.push_str(" moves");
+        for uci_move in moves {
+            command.push_str(&format!(" {}", uci_move));
+        }
+    }
+    command
+}
+
+pub fn parse_engine_line(line: &str) -> Option<EngineEvent> {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        "id" => {
+            let name = words.next()?.to_string();
+            let value = words.collect::<Vec<_>>().join(" ");
+            Some(EngineEvent::Id { name, value })
+        }
+        "option" => Some(EngineEvent::OptionDeclared(
+            line.trim_start()["option".len()..].trim().to_string(),
+        )),
+        "uciok" => Some(EngineEvent::UciOk),
+        "readyok" => Some(EngineEvent::ReadyOk),
+        "info" => Some(EngineEvent::Info(parse_info(words))),
+        "bestmove" => Some(EngineEvent::BestMove(parse_best_move(words))),
+        _ => None,
+    }
+}
+
+fn parse_info<'a>(words: impl Iterator<Item = &'a str>) -> EngineInfo {
+    let mut info = EngineInfo::default();
+    let mut words = words.peekable();
+
+    while let Some(word) = words.next() {
+        match word {
+            "depth" => info.depth = words.next().and_then(|value| value.parse().ok()),
+            "seldepth" => info.seldepth = words.next().and_then(|value| value.parse().ok()),
+            "multipv" => info.multipv = words.next().and_then(|value| value.parse().ok()),
+            "nodes" => info.nodes = words.next().and_then(|value| value.parse().ok()),
+            "nps" => info.nps = words.next().and_then(|value| value.parse().ok()),
+            "time" => info.time_ms = words.next().and_then(|value| value.parse().ok()),
+            "score" => {
+                let kind = words.next();
+                let value = words.next().and_then(|value| value.parse::<i32>().ok());
+                let score = match (kind, value) {
+                    (Some("cp"), Some(value)) => Some(Score::Centipawns(value)),
+                    (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                    _ => None,
+                };
+                info.score = score.map(|score| (score, ScoreBound::Exact));
+            }
+            "lowerbound" => {
+                info.score = info.score.map(|(score, _)| (score, ScoreBound::Lower));
+            }
+            "upperbound" => {
+                info.score = info.score.map(|(score, _)| (score, ScoreBound::Upper));
+            }
+            "pv" => {
+                info.pv.clear();
+                // The first word which is not a move starts the next field.
+                while let Some(uci_move) = words
+                    .peek()
+                    .and_then(|value| Uci::from_ascii(value.as_bytes()).ok())
+                {
+                    info.pv.push(uci_move);
+                    words.next();
+                }
+            }
+            "string" => {
+                info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+            }
+            _ => {}
+        }
+    }
+
+    info
+}
+
+fn parse_best_move<'a>(mut words: impl Iterator<Item = &'a str>) -> BestMove {
+    let best_move = words
+        .next()
+        .filter(|value| *value != "(none)" && *value != "0000")
+        .and_then(|value| Uci::from_ascii(value.as_bytes()).ok());
+    let ponder = match words.next() {
+        Some("ponder") => words
+            .next()
+            .and_then(|value| Uci::from_ascii(value.as_bytes()).ok()),
+        _ => None,
+    };
+
+    BestMove { best_move, ponder }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(text: &str) -> Uci {
+        Uci::from_ascii(text.as_bytes()).unwrap()
+    }
+
+    fn parse_info_line(line: &str) -> EngineInfo {
+        match parse_engine_line(line) {
+            Some(EngineEvent::Info(info)) => info,
+            other => panic!("Expected an info event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_handshake_lines() {
+        assert_eq!(
+            parse_engine_line("id name Stockfish 11"),
+            Some(EngineEvent::Id {
+                name: "name".to_string(),
+                value: "Stockfish 11".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_engine_line("option name Hash type spin default 16 min 1 max 131072"),
+            Some(EngineEvent::OptionDeclared(
+                "name Hash type spin default 16 min 1 max 131072".to_string()
+            ))
+        );
+        assert_eq!(parse_engine_line("uciok"), Some(EngineEvent::UciOk));
+        assert_eq!(parse_engine_line("readyok"), Some(EngineEvent::ReadyOk));
+        assert_eq!(
+            parse_engine_line("Stockfish by the Stockfish developers"),
+            None
+        );
+        assert_eq!(parse_engine_line(""), None);
+    }
+
+    #[test]
+    fn parses_info_fields() {
+        let info = parse_info_line(
+            "info depth 20 seldepth 28 multipv 1 score cp -35 nodes 2400000 nps 1200000 time 2000 pv e7e5 g1f3 b8c6",
+        );
+
+        assert_eq!(info.depth, Some(20));
+        assert_eq!(info.seldepth, Some(28));
+        assert_eq!(info.multipv, Some(1));
+        assert_eq!(
+            info.score,
+            Some((Score::Centipawns(-35), ScoreBound::Exact))
+        );
+        assert_eq!(info.nodes, Some(2_400_000));
+        assert_eq!(info.nps, Some(1_200_000));
+        assert_eq!(info.time_ms, Some(2000));
+        assert_eq!(info.pv, vec![uci("e7e5"), uci("g1f3"), uci("b8c6")]);
+        assert_eq!(info.string, None);
+    }
+
+    #[test]
+    fn parses_mate_scores_and_bounds() {
+        let info = parse_info_line("info depth 30 score mate -4 upperbound");
+        assert_eq!(info.score, Some((Score::Mate(-4), ScoreBound::Upper)));
+
+        let info = parse_info_line("info depth 30 score cp 120 lowerbound");
+        assert_eq!(
+            info.score,
+            Some((Score::Centipawns(120), ScoreBound::Lower))
+        );
+    }
+
+    #[test]
+    fn keeps_the_fields_following_the_pv() {
+        let info = parse_info_line("info depth 9 pv e2e4 e7e5 multipv 2 score cp 15");
+        assert_eq!(info.pv, vec![uci("e2e4"), uci("e7e5")]);
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some((Score::Centipawns(15), ScoreBound::Exact)));
+
+        let info = parse_info_line("info pv a7a8q string promoting now");
+        assert_eq!(info.pv, vec![uci("a7a8q")]);
+        assert_eq!(info.string, Some("promoting now".to_string()));
+    }
+
+    #[test]
+    fn parses_best_moves() {
+        assert_eq!(
+            parse_engine_line("bestmove e2e4 ponder e7e5"),
+            Some(EngineEvent::BestMove(BestMove {
+                best_move: Some(uci("e2e4")),
+                ponder: Some(uci("e7e5")),
+            }))
+        );
+        assert_eq!(
+            parse_engine_line("bestmove g1f3"),
+            Some(EngineEvent::BestMove(BestMove {
+                best_move: Some(uci("g1f3")),
+                ponder: None,
+            }))
+        );
+        assert_eq!(
+            parse_engine_line("bestmove (none)"),
+            Some(EngineEvent::BestMove(BestMove {
+                best_move: None,
+                ponder: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn builds_position_and_go_commands() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(
+            position_command(start, &[]),
+            format!("position fen {}", start)
+        );
+        assert_eq!(
+            position_command(start, &[uci("e2e4"), uci("c7c5")]),
+            format!("position fen {} moves e2e4 c7c5", start)
+        );
+
+        assert_eq!(SearchLimit::Depth(18).to_go_command(), "go depth 18");
+        assert_eq!(
+            SearchLimit::MoveTime(500).to_go_command(),
+            "go movetime 500"
+        );
+        assert_eq!(SearchLimit::Nodes(10000).to_go_command(), "go nodes 10000");
+        assert_eq!(SearchLimit::Infinite.to_go_command(), "go infinite");
+    }
+}
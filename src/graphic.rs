@@ -1,3 +1,7 @@
 mod chess_board;
+mod engine_runner;
+mod evaluation_bar;
 mod main_window;
+mod move_list;
+mod statistics_view;
 pub use main_window::start;
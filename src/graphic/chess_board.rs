@@ -1,4 +1,8 @@
 mod chess_board_painter;
 mod chess_board_widget;
 mod drag_and_drop_handlers;
+mod line_animation;
+mod offscreen_rendering;
 pub use chess_board_widget::*;
+pub use line_animation::*;
+pub use offscreen_rendering::*;
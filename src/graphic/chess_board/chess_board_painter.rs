@@ -1,13 +1,17 @@
 use failure::Fail;
 
-use cairo::{Context, FontFace, FontWeight, ImageSurface};
+use cairo::{Context, FontFace, FontWeight, ImageSurface, RadialGradient};
 use resvg::backend_cairo::render_to_image;
 use resvg::usvg::ShapeRendering;
 use resvg::{usvg::Tree, FitTo, Options};
 use shakmaty::fen::fen;
+use shakmaty::{Color, Outcome, Position, Setup};
 use std::collections::HashMap;
 
-use super::chess_board_widget::{BlackSide, ChessState, DndState};
+use super::chess_board_widget::{
+    BlackSide, BoardCellCoord, ChessState, DndState, LastMove, SelectionState,
+};
+use crate::pgn::GraphicalAnnotations;
 
 #[derive(Debug, Fail)]
 pub enum ChessPiecesError {
@@ -114,14 +118,28 @@ impl ChessBoardPainter {
         self.pieces_images.build_images(self.cells_size);
     }
 
-    pub fn paint(&self, context: &Context, chess_state: &ChessState, dnd_state: &DndState) {
+    pub fn paint(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        dnd_state: &DndState,
+        selection_state: &SelectionState,
+    ) {
         self.draw_background(context, chess_state);
         self.draw_coordinates(context, chess_state);
         self.draw_player_turn(context, chess_state);
-        self.draw_cells(context, chess_state, dnd_state);
+        self.draw_cells(context, chess_state, dnd_state, selection_state);
+        self.draw_check_glow(context, chess_state);
+        self.draw_pgn_annotations_squares(context, chess_state);
         self.draw_pieces(context, chess_state, dnd_state);
+        self.draw_legal_moves_hints(context, chess_state, dnd_state);
         self.draw_last_move(context, chess_state);
+        self.draw_pgn_annotations_arrows(context, chess_state);
+        self.draw_user_annotations(context, chess_state);
+        self.draw_annotations_arrows(context, chess_state, &chess_state.engine_annotations);
+        self.draw_variation_preview(context, chess_state);
         self.draw_cursor_piece(context, chess_state, dnd_state);
+        self.draw_game_over_banner(context, chess_state);
     }
 
     fn draw_background(&self, context: &Context, chess_state: &ChessState) {
@@ -132,18 +150,154 @@ impl ChessBoardPainter {
         context.paint();
     }
 
-    fn draw_cells(&self, context: &Context, chess_state: &ChessState, dnd_state: &DndState) {
+    fn draw_cells(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        dnd_state: &DndState,
+        selection_state: &SelectionState,
+    ) {
         let cells_size = self.cells_size as f64;
 
         for row in 0..8 {
             for col in 0..8 {
                 setup_current_cell_color(context, col, row, chess_state);
+                setup_selection_highlight_if_matches(
+                    context,
+                    col,
+                    row,
+                    chess_state,
+                    selection_state,
+                );
                 setup_dnd_highlight_if_matches(context, col, row, chess_state, dnd_state);
                 fill_current_cell_to_setup(context, col, row, cells_size);
             }
         }
     }
 
+    fn draw_check_glow(&self, context: &Context, chess_state: &ChessState) {
+        let board = &chess_state.board;
+        if !board.is_check() {
+            return;
+        }
+
+        if let Some(king_square) = board.board().king_of(board.turn()) {
+            let king_cell = BoardCellCoord::from_square(king_square);
+            let col = self.get_col(king_cell.file, chess_state.black_side) as f64;
+            let row = self.get_row(7 - king_cell.rank, chess_state.black_side) as f64;
+            let cells_size = self.cells_size as f64;
+            let center_x = cells_size * (1.0 + col);
+            let center_y = cells_size * (1.0 + row);
+            let radius = cells_size * 0.5;
+
+            let (glow_red, glow_green, glow_blue) = chess_state.check_glow_color;
+            let gradient = RadialGradient::new(center_x, center_y, 0.0, center_x, center_y, radius);
+            gradient.add_color_stop_rgba(0.0, glow_red, glow_green, glow_blue, 1.0);
+            gradient.add_color_stop_rgba(0.6, glow_red, glow_green, glow_blue, 0.6);
+            gradient.add_color_stop_rgba(1.0, glow_red, glow_green, glow_blue, 0.0);
+
+            context.save();
+            context.set_source(&gradient);
+            context.rectangle(
+                center_x - radius,
+                center_y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            );
+            context.fill();
+            context.restore();
+        }
+    }
+
+    fn current_pgn_annotations<'a>(
+        &self,
+        chess_state: &'a ChessState,
+    ) -> Option<&'a GraphicalAnnotations> {
+        let session = chess_state.session.as_ref()?;
+        let node = chess_state.current_node()?;
+        Some(&session.tree().node(node).annotations)
+    }
+
+    fn draw_pgn_annotations_squares(&self, context: &Context, chess_state: &ChessState) {
+        if let Some(annotations) = self.current_pgn_annotations(chess_state) {
+            self.draw_annotations_squares(context, chess_state, annotations);
+        }
+    }
+
+    fn draw_pgn_annotations_arrows(&self, context: &Context, chess_state: &ChessState) {
+        if let Some(annotations) = self.current_pgn_annotations(chess_state) {
+            self.draw_annotations_arrows(context, chess_state, annotations);
+        }
+    }
+
+    fn draw_user_annotations(&self, context: &Context, chess_state: &ChessState) {
+        let annotations = &chess_state.user_annotations;
+        let cells_size = self.cells_size as f64;
+        let alpha = 0.8;
+
+        context.set_line_width(cells_size * 0.08);
+        for highlight in &annotations.squares {
+            let cell = BoardCellCoord::from_square(highlight.square);
+            let col = self.get_col(cell.file, chess_state.black_side) as f64;
+            let row = self.get_row(7 - cell.rank, chess_state.black_side) as f64;
+            let (red, green, blue) = highlight.color.rgb();
+
+            context.set_source_rgba(red, green, blue, alpha);
+            context.new_path();
+            context.arc(
+                cells_size * (1.0 + col),
+                cells_size * (1.0 + row),
+                cells_size * 0.45,
+                0f64,
+                2f64 * std::f64::consts::PI,
+            );
+            context.stroke();
+        }
+
+        self.draw_annotations_arrows(context, chess_state, annotations);
+    }
+
+    fn draw_annotations_squares(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        annotations: &GraphicalAnnotations,
+    ) {
+        let cells_size = self.cells_size as f64;
+        let alpha = 0.6;
+
+        for highlight in &annotations.squares {
+            let cell = BoardCellCoord::from_square(highlight.square);
+            let col = self.get_col(cell.file, chess_state.black_side) as f64;
+            let row = self.get_row(7 - cell.rank, chess_state.black_side) as f64;
+            let (red, green, blue) = highlight.color.rgb();
+
+            context.set_source_rgba(red, green, blue, alpha);
+            context.rectangle(
+                cells_size * (0.5 + col),
+                cells_size * (0.5 + row),
+                cells_size,
+                cells_size,
+            );
+            context.fill();
+        }
+    }
+
+    fn draw_annotations_arrows(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        annotations: &GraphicalAnnotations,
+    ) {
+        for arrow in &annotations.arrows {
+            let arrow_move = LastMove {
+                origin: BoardCellCoord::from_square(arrow.from),
+                target: BoardCellCoord::from_square(arrow.to),
+            };
+            self.draw_move_arrow(context, chess_state, &arrow_move, arrow.color.rgb());
+        }
+    }
+
     fn draw_pieces(&self, context: &Context, chess_state: &ChessState, dnd_state: &DndState) {
         let position = fen(&chess_state.board);
         let position = position.as_str();
@@ -162,7 +316,72 @@ impl ChessBoardPainter {
         }
     }
 
+    fn draw_legal_moves_hints(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        dnd_state: &DndState,
+    ) {
+        if !chess_state.show_legal_moves_hints || !dnd_state.dnd_active {
+            return;
+        }
+
+        let mut hints = vec![];
+        for legal_move in chess_state.board.legal_moves() {
+            if let Some(move_cells) = LastMove::from_move(&legal_move) {
+                let from_dragged_piece = move_cells.origin.file == dnd_state.origin_file
+                    && move_cells.origin.rank == dnd_state.origin_rank;
+                let hint = (
+                    move_cells.target.file,
+                    move_cells.target.rank,
+                    legal_move.is_capture(),
+                );
+                if from_dragged_piece && !hints.contains(&hint) {
+                    hints.push(hint);
+                }
+            }
+        }
+
+        let (hint_red, hint_green, hint_blue) = chess_state.legal_moves_hints_color;
+        let alpha = 0.45;
+        let cells_size = self.cells_size as f64;
+        context.set_source_rgba(hint_red, hint_green, hint_blue, alpha);
+
+        for (file, rank, is_capture) in hints {
+            let col = self.get_col(file, chess_state.black_side) as f64;
+            let row = self.get_row(7 - rank, chess_state.black_side) as f64;
+            let center_x = cells_size * (1.0 + col);
+            let center_y = cells_size * (1.0 + row);
+
+            context.new_path();
+            if is_capture {
+                context.set_line_width(cells_size * 0.08);
+                context.arc(
+                    center_x,
+                    center_y,
+                    cells_size * 0.44,
+                    0f64,
+                    2f64 * std::f64::consts::PI,
+                );
+                context.stroke();
+            } else {
+                context.arc(
+                    center_x,
+                    center_y,
+                    cells_size * 0.15,
+                    0f64,
+                    2f64 * std::f64::consts::PI,
+                );
+                context.fill();
+            }
+        }
+    }
+
     fn draw_player_turn(&self, context: &Context, chess_state: &ChessState) {
+        if chess_state.game_over.is_some() {
+            return;
+        }
+
         let position = fen(&chess_state.board);
         let position = position.as_str();
         let turn_str = position.split(" ").skip(1).take(1).collect::<Vec<_>>()[0];
@@ -190,53 +409,124 @@ impl ChessBoardPainter {
 
     fn draw_last_move(&self, context: &Context, chess_state: &ChessState) {
         if let Some(last_move) = &chess_state.last_move {
-            let (lm_red, lm_green, lm_blue) = chess_state.last_move_arrow_color;
-            let alpha = 0.7;
+            self.draw_move_arrow(
+                context,
+                chess_state,
+                last_move,
+                chess_state.last_move_arrow_color,
+            );
+        }
+    }
+
+    fn draw_variation_preview(&self, context: &Context, chess_state: &ChessState) {
+        if let Some(preview_move) = &chess_state.variation_preview {
+            self.draw_move_arrow(
+                context,
+                chess_state,
+                preview_move,
+                chess_state.variation_preview_arrow_color,
+            );
+        }
+    }
+
+    fn draw_move_arrow(
+        &self,
+        context: &Context,
+        chess_state: &ChessState,
+        move_to_draw: &LastMove,
+        color: (f64, f64, f64),
+    ) {
+        let (arrow_red, arrow_green, arrow_blue) = color;
+        let alpha = 0.7;
 
-            let origin_file = last_move.origin.file as f64;
-            let origin_rank = last_move.origin.rank as f64;
+        let origin_file = move_to_draw.origin.file as f64;
+        let origin_rank = move_to_draw.origin.rank as f64;
 
-            let target_file = last_move.target.file as f64;
-            let target_rank = last_move.target.rank as f64;
+        let target_file = move_to_draw.target.file as f64;
+        let target_rank = move_to_draw.target.rank as f64;
 
-            let cells_size = self.cells_size as f64;
+        let cells_size = self.cells_size as f64;
 
-            let origin_x = if chess_state.black_side == BlackSide::BlackBottom {
-                cells_size * (8.0 - origin_file)
-            } else {
-                cells_size * (1.0 + origin_file)
-            };
+        let origin_x = if chess_state.black_side == BlackSide::BlackBottom {
+            cells_size * (8.0 - origin_file)
+        } else {
+            cells_size * (1.0 + origin_file)
+        };
 
-            let origin_y = if chess_state.black_side == BlackSide::BlackBottom {
-                cells_size * (1.0 + origin_rank)
-            } else {
-                cells_size * (8.0 - origin_rank)
-            };
+        let origin_y = if chess_state.black_side == BlackSide::BlackBottom {
+            cells_size * (1.0 + origin_rank)
+        } else {
+            cells_size * (8.0 - origin_rank)
+        };
 
-            let target_x = if chess_state.black_side == BlackSide::BlackBottom {
-                cells_size * (8.0 - target_file)
-            } else {
-                cells_size * (1.0 + target_file)
-            };
+        let target_x = if chess_state.black_side == BlackSide::BlackBottom {
+            cells_size * (8.0 - target_file)
+        } else {
+            cells_size * (1.0 + target_file)
+        };
 
-            let target_y = if chess_state.black_side == BlackSide::BlackBottom {
-                cells_size * (1.0 + target_rank)
+        let target_y = if chess_state.black_side == BlackSide::BlackBottom {
+            cells_size * (1.0 + target_rank)
+        } else {
+            cells_size * (8.0 - target_rank)
+        };
+
+        context.set_source_rgba(arrow_red, arrow_green, arrow_blue, alpha);
+        context.set_line_width(self.cells_size as f64 * 0.10);
+
+        self.draw_arrow(
+            context,
+            origin_x,
+            origin_y,
+            target_x,
+            target_y,
+            cells_size * 0.5,
+            cells_size * 0.5,
+        );
+    }
+
+    fn draw_game_over_banner(&self, context: &Context, chess_state: &ChessState) {
+        if let Some(outcome) = chess_state.game_over {
+            let board = &chess_state.board;
+            let reason = if board.is_checkmate() {
+                "Checkmate"
+            } else if board.is_stalemate() {
+                "Stalemate"
+            } else if board.is_insufficient_material() {
+                "Insufficient material"
             } else {
-                cells_size * (8.0 - target_rank)
+                "Fifty moves rule"
             };
+            let result = match outcome {
+                Outcome::Decisive {
+                    winner: Color::White,
+                } => "1-0",
+                Outcome::Decisive {
+                    winner: Color::Black,
+                } => "0-1",
+                Outcome::Draw => "1/2-1/2",
+            };
+            let text = format!("{} : {}", reason, result);
 
-            context.set_source_rgba(lm_red, lm_green, lm_blue, alpha);
-            context.set_line_width(self.cells_size as f64 * 0.10);
-
-            self.draw_arrow(
-                context,
-                origin_x,
-                origin_y,
-                target_x,
-                target_y,
-                cells_size * 0.5,
+            let cells_size = self.cells_size as f64;
+            let (banner_red, banner_green, banner_blue) = chess_state.game_over_banner_color;
+            context.set_source_rgba(banner_red, banner_green, banner_blue, 0.8);
+            context.rectangle(
                 cells_size * 0.5,
+                cells_size * 4.0,
+                cells_size * 8.0,
+                cells_size,
             );
+            context.fill();
+
+            context.set_source_rgb(1.0, 1.0, 1.0);
+            context.set_font_size(cells_size * 0.4);
+            let extents = context.text_extents(text.as_str());
+            context.move_to(
+                cells_size * 4.5 - extents.width / 2.0 - extents.x_bearing,
+                cells_size * 4.5 - extents.height / 2.0 - extents.y_bearing,
+            );
+            context.show_text(text.as_str());
         }
     }
 
@@ -577,6 +867,33 @@ fn setup_dnd_highlight_if_matches(
     }
 }
 
+fn setup_selection_highlight_if_matches(
+    context: &Context,
+    col: i8,
+    row: i8,
+    chess_state: &ChessState,
+    selection_state: &SelectionState,
+) {
+    let (start_cell_red, start_cell_green, start_cell_blue) = chess_state.dnd_start_cell_color;
+    let file = if chess_state.black_side == BlackSide::BlackBottom {
+        7 - col
+    } else {
+        col
+    } as u8;
+    let rank = if chess_state.black_side == BlackSide::BlackBottom {
+        row
+    } else {
+        7 - row
+    } as u8;
+
+    if selection_state.selection_active
+        && file == selection_state.file
+        && rank == selection_state.rank
+    {
+        context.set_source_rgb(start_cell_red, start_cell_green, start_cell_blue);
+    }
+}
+
 fn fill_current_cell_to_setup(context: &Context, col: i8, row: i8, cells_size: f64) {
     let cell_x = cells_size * (0.5 + (col as f64));
     let cell_y = cells_size * (0.5 + (row as f64));
@@ -1,16 +1,27 @@
 use gdk::EventMask;
 use gtk::prelude::*;
 use gtk::Inhibit;
-use relm::Widget;
+use relm::{timeout, Channel, EventStream, Relm, Widget};
 use relm_derive::{widget, Msg};
-use shakmaty::Chess;
+use shakmaty::fen::fen;
+use shakmaty::san::SanPlus;
+use shakmaty::uci::Uci;
+use shakmaty::{Chess, Move, Outcome, Position, Role, Setup, Square};
+
+use crate::pgn::{position_from_fen, GameTree, GraphicalAnnotations, NodeId, PgnGame};
+use crate::reviser::{PlayerKind, ReviserSession, Verdict};
 
 use super::drag_and_drop_handlers::*;
 
 use std::cell::RefCell;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::thread;
+use std::time::Instant;
 
 use super::chess_board_painter::ChessBoardPainter;
+use super::line_animation::{line_frames, save_gif, AnimationFrame, AnimationSettings};
+use super::offscreen_rendering::{save_diagram, RenderError, RenderOptions};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum BlackSide {
@@ -18,16 +29,91 @@ pub enum BlackSide {
     BlackBottom,
 }
 
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct BoardCellCoord {
     pub file: u8,
     pub rank: u8,
 }
 
+impl BoardCellCoord {
+    pub fn from_square(square: Square) -> Self {
+        BoardCellCoord {
+            file: square.file().char() as u8 - b'a',
+            rank: square.rank().char() as u8 - b'1',
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct LastMove {
     pub origin: BoardCellCoord,
     pub target: BoardCellCoord,
 }
 
+impl LastMove {
+    pub fn from_move(played_move: &Move) -> Option<Self> {
+        LastMove::from_uci(&Uci::from_standard(played_move))
+    }
+
+    pub fn from_uci(uci: &Uci) -> Option<Self> {
+        match uci {
+            Uci::Normal { from, to, .. } => Some(LastMove {
+                origin: BoardCellCoord::from_square(*from),
+                target: BoardCellCoord::from_square(*to),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HistoryEntry {
+    pub board: Chess,
+    pub last_move: Option<LastMove>,
+    pub node: Option<NodeId>,
+}
+
+pub struct PlayedMove {
+    pub uci: String,
+    pub san: String,
+    pub fen: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Guess {
+    pub node: NodeId,
+    pub fen: String,
+    pub guessed_uci: String,
+    pub expected_uci: String,
+    pub verdict: Verdict,
+    pub time_taken_ms: u64,
+}
+
+pub enum MoveAttempt {
+    Played(PlayedMove, Option<Guess>),
+    Rejected(Guess),
+    Ignored,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SessionSettings {
+    pub white_player: PlayerKind,
+    pub black_player: PlayerKind,
+    pub computer_delay_ms: u32,
+    pub engine_continuation: bool,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        SessionSettings {
+            white_player: PlayerKind::Human,
+            black_player: PlayerKind::Human,
+            computer_delay_ms: 800,
+            engine_continuation: false,
+        }
+    }
+}
+
 pub struct ChessState {
     pub size: u32,
     pub background_color: (f64, f64, f64),
@@ -38,10 +124,25 @@ pub struct ChessState {
     pub dnd_end_cell_color: (f64, f64, f64),
     pub dnd_cross_color: (f64, f64, f64),
     pub last_move_arrow_color: (f64, f64, f64),
+    pub variation_preview_arrow_color: (f64, f64, f64),
+    pub legal_moves_hints_color: (f64, f64, f64),
+    pub show_legal_moves_hints: bool,
+    pub game_over_banner_color: (f64, f64, f64),
+    pub check_glow_color: (f64, f64, f64),
     pub board: Chess,
     pub black_side: BlackSide,
     pub last_move: Option<LastMove>,
     pub pending_promotion: bool,
+    pub interactive: bool,
+    pub game_over: Option<Outcome>,
+    pub session: Option<ReviserSession>,
+    pub engine_continuation: bool,
+    pub history: Vec<HistoryEntry>,
+    pub history_index: usize,
+    pub user_annotations: GraphicalAnnotations,
+    pub engine_annotations: GraphicalAnnotations,
+    pub variation_preview: Option<LastMove>,
+    pub position_shown_at: Instant,
 }
 
 #[derive(Default)]
@@ -54,12 +155,187 @@ pub struct DndState {
     pub target_file: u8,
     pub target_rank: u8,
     pub moved_piece_fen: char,
+    pub annotation_origin: Option<BoardCellCoord>,
+}
+
+#[derive(Default)]
+pub struct SelectionState {
+    pub selection_active: bool,
+    pub file: u8,
+    pub rank: u8,
 }
 
 #[allow(dead_code)]
 pub struct ChessBoardModel {
+    relm: Relm<ChessBoard>,
     chess_state: Rc<RefCell<ChessState>>,
     dnd_state: Rc<RefCell<DndState>>,
+    selection_state: Rc<RefCell<SelectionState>>,
+    computer_delay_ms: u32,
+    session_generation: u32,
+    variation_chooser: Option<gtk::Popover>,
+    promotion_chooser: Option<gtk::Popover>,
+    animation_export: Option<Channel<Result<(), String>>>,
+}
+
+impl ChessState {
+    pub fn play_move(&mut self, played_move: &Move) -> PlayedMove {
+        let san = SanPlus::from_move(self.board.clone(), played_move).to_string();
+        self.board.play_unchecked(played_move);
+        self.last_move = LastMove::from_move(played_move);
+        self.user_annotations = GraphicalAnnotations::default();
+        self.engine_annotations = GraphicalAnnotations::default();
+        self.push_history_entry();
+
+        PlayedMove {
+            uci: Uci::from_standard(played_move).to_string(),
+            san,
+            fen: fen(&self.board),
+        }
+    }
+
+    pub fn current_node(&self) -> Option<NodeId> {
+        let session = self.session.as_ref()?;
+        if session.is_in_book() && fen(session.current_position()) == fen(&self.board) {
+            Some(session.cursor())
+        } else {
+            None
+        }
+    }
+
+    pub fn is_out_of_book(&self) -> bool {
+        match &self.session {
+            Some(session) => self.current_node().is_none() || session.is_line_finished(),
+            None => false,
+        }
+    }
+
+    pub fn player_to_move(&self) -> Option<PlayerKind> {
+        self.session
+            .as_ref()
+            .map(|session| session.player_kind(self.board.turn()))
+    }
+
+    pub fn guess(&self, guessed_move: &Move) -> Option<Guess> {
+        let session = self.session.as_ref()?;
+        let node = self.current_node()?;
+        if session.player_to_move() != PlayerKind::Human {
+            return None;
+        }
+        let expected_moves = session.expected_moves();
+        let verdict = match expected_moves
+            .iter()
+            .position(|expected_move| expected_move == guessed_move)
+        {
+            Some(0) => Verdict::MainLine,
+            Some(child_index) => Verdict::Variation(child_index),
+            None => Verdict::Wrong,
+        };
+
+        Some(Guess {
+            node,
+            fen: fen(&self.board),
+            guessed_uci: Uci::from_standard(guessed_move).to_string(),
+            expected_uci: Uci::from_standard(expected_moves.first()?).to_string(),
+            verdict,
+            time_taken_ms: self.position_shown_at.elapsed().as_millis() as u64,
+        })
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        let mut annotations = match (&self.session, self.current_node()) {
+            (Some(session), Some(node)) => session.tree().node(node).annotations.clone(),
+            _ => GraphicalAnnotations::default(),
+        };
+        annotations.merge(self.user_annotations.clone());
+        annotations.merge(self.engine_annotations.clone());
+
+        RenderOptions {
+            size: self.size,
+            black_side: self.black_side,
+            background_color: self.background_color,
+            white_cells_color: self.white_cells_color,
+            black_cells_color: self.black_cells_color,
+            coordinates_color: self.coordinates_color,
+            last_move_arrow_color: self.last_move_arrow_color,
+            last_move: self.last_move,
+            annotations,
+        }
+    }
+
+    pub fn current_history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            board: self.board.clone(),
+            last_move: self.last_move,
+            node: self.current_node(),
+        }
+    }
+
+    pub fn reset_history(&mut self) {
+        self.history = vec![self.current_history_entry()];
+        self.history_index = 0;
+    }
+
+    fn push_history_entry(&mut self) {
+        let entry = self.current_history_entry();
+        self.history.truncate(self.history_index + 1);
+        self.history.push(entry);
+        self.history_index = self.history.len() - 1;
+    }
+
+    pub fn go_to_history_entry(&mut self, index: usize) -> bool {
+        let entry = match self.history.get(index) {
+            Some(entry) => entry.clone(),
+            None => return false,
+        };
+
+        self.history_index = index;
+        self.board = entry.board;
+        self.last_move = entry.last_move;
+        self.pending_promotion = false;
+        self.user_annotations = GraphicalAnnotations::default();
+        self.engine_annotations = GraphicalAnnotations::default();
+        if let (Some(session), Some(node)) = (self.session.as_mut(), entry.node) {
+            session.set_cursor(node);
+        }
+        self.game_over = self.detect_outcome();
+
+        true
+    }
+
+    pub fn rebuild_history_to_node(&mut self, node: NodeId) {
+        if let Some(session) = self.session.as_mut() {
+            session.set_cursor(node);
+            let tree = session.tree();
+            self.history = std::iter::once(GameTree::ROOT)
+                .chain(tree.path_to(node))
+                .map(|id| HistoryEntry {
+                    board: tree.node(id).position.clone(),
+                    last_move: tree
+                        .node(id)
+                        .move_played
+                        .as_ref()
+                        .and_then(LastMove::from_move),
+                    node: Some(id),
+                })
+                .collect();
+            self.history_index = self.history.len() - 1;
+        }
+    }
+
+    pub fn is_at_history_end(&self) -> bool {
+        self.history_index + 1 == self.history.len()
+    }
+
+    pub fn detect_outcome(&self) -> Option<Outcome> {
+        self.board.outcome().or_else(|| {
+            if self.board.halfmoves() >= 100 {
+                Some(Outcome::Draw)
+            } else {
+                None
+            }
+        })
+    }
 }
 
 pub struct ChessStateBuilder {
@@ -72,12 +348,17 @@ pub struct ChessStateBuilder {
     dnd_end_cell_color: (f64, f64, f64),
     dnd_cross_color: (f64, f64, f64),
     last_move_arrow_color: (f64, f64, f64),
+    variation_preview_arrow_color: (f64, f64, f64),
+    legal_moves_hints_color: (f64, f64, f64),
+    show_legal_moves_hints: bool,
+    game_over_banner_color: (f64, f64, f64),
+    check_glow_color: (f64, f64, f64),
     black_side: BlackSide,
 }
 
 #[allow(dead_code)]
 impl ChessStateBuilder {
-    fn new() -> Self {
+    pub(super) fn new() -> Self {
         ChessStateBuilder {
             size: 300,
             background_color: (0.5, 0.4, 0.9),
@@ -88,11 +369,16 @@ impl ChessStateBuilder {
             dnd_end_cell_color: (0.34, 0.82, 0.14),
             dnd_cross_color: (0.70, 0.18, 0.90),
             last_move_arrow_color: (0.25, 0.36, 0.93),
+            variation_preview_arrow_color: (0.95, 0.60, 0.10),
+            legal_moves_hints_color: (0.15, 0.15, 0.15),
+            show_legal_moves_hints: true,
+            game_over_banner_color: (0.10, 0.10, 0.30),
+            check_glow_color: (0.95, 0.05, 0.05),
             black_side: BlackSide::BlackTop,
         }
     }
 
-    fn build(self) -> ChessState {
+    pub(super) fn build(self) -> ChessState {
         ChessState {
             size: self.size,
             background_color: self.background_color,
@@ -103,34 +389,53 @@ impl ChessStateBuilder {
             dnd_end_cell_color: self.dnd_end_cell_color,
             dnd_cross_color: self.dnd_cross_color,
             last_move_arrow_color: self.last_move_arrow_color,
+            variation_preview_arrow_color: self.variation_preview_arrow_color,
+            legal_moves_hints_color: self.legal_moves_hints_color,
+            show_legal_moves_hints: self.show_legal_moves_hints,
+            game_over_banner_color: self.game_over_banner_color,
+            check_glow_color: self.check_glow_color,
             black_side: self.black_side,
             board: Chess::default(),
             last_move: None,
             pending_promotion: false,
+            interactive: true,
+            game_over: None,
+            session: None,
+            engine_continuation: false,
+            history: vec![HistoryEntry {
+                board: Chess::default(),
+                last_move: None,
+                node: None,
+            }],
+            history_index: 0,
+            user_annotations: GraphicalAnnotations::default(),
+            engine_annotations: GraphicalAnnotations::default(),
+            variation_preview: None,
+            position_shown_at: Instant::now(),
         }
     }
 
-    fn set_board_size(&mut self, size: u32) {
+    pub(super) fn set_board_size(&mut self, size: u32) {
         self.size = size;
     }
 
-    fn set_board_background_color(&mut self, background_color: (f64, f64, f64)) {
+    pub(super) fn set_board_background_color(&mut self, background_color: (f64, f64, f64)) {
         self.background_color = background_color;
     }
 
-    fn set_board_white_cells_color(&mut self, white_cells_color: (f64, f64, f64)) {
+    pub(super) fn set_board_white_cells_color(&mut self, white_cells_color: (f64, f64, f64)) {
         self.white_cells_color = white_cells_color;
     }
 
-    fn set_board_black_cells_color(&mut self, black_cells_color: (f64, f64, f64)) {
+    pub(super) fn set_board_black_cells_color(&mut self, black_cells_color: (f64, f64, f64)) {
         self.black_cells_color = black_cells_color;
     }
 
-    fn set_board_coordinates_color(&mut self, coordinates_color: (f64, f64, f64)) {
+    pub(super) fn set_board_coordinates_color(&mut self, coordinates_color: (f64, f64, f64)) {
         self.coordinates_color = coordinates_color;
     }
 
-    fn set_board_orientation(&mut self, side: BlackSide) {
+    pub(super) fn set_board_orientation(&mut self, side: BlackSide) {
         self.black_side = side;
     }
 
@@ -146,27 +451,109 @@ impl ChessStateBuilder {
         self.dnd_cross_color = cross_color;
     }
 
-    fn set_last_move_arrow_color(&mut self, last_move_arrow_color: (f64, f64, f64)) {
+    pub(super) fn set_last_move_arrow_color(&mut self, last_move_arrow_color: (f64, f64, f64)) {
         self.last_move_arrow_color = last_move_arrow_color;
     }
+
+    fn set_variation_preview_arrow_color(&mut self, preview_arrow_color: (f64, f64, f64)) {
+        self.variation_preview_arrow_color = preview_arrow_color;
+    }
+
+    fn set_legal_moves_hints_color(&mut self, hints_color: (f64, f64, f64)) {
+        self.legal_moves_hints_color = hints_color;
+    }
+
+    fn set_show_legal_moves_hints(&mut self, show_hints: bool) {
+        self.show_legal_moves_hints = show_hints;
+    }
+
+    fn set_game_over_banner_color(&mut self, banner_color: (f64, f64, f64)) {
+        self.game_over_banner_color = banner_color;
+    }
+
+    fn set_check_glow_color(&mut self, check_glow_color: (f64, f64, f64)) {
+        self.check_glow_color = check_glow_color;
+    }
 }
 
 #[derive(Msg)]
 pub enum ChessBoardMsg {
     SetBlackSide(BlackSide),
+    SetPosition(String),
+    PlayMove(String),
+    PlayEngineMove {
+        fen: String,
+        uci: String,
+    },
+    SetInteractive(bool),
+    SetLastMove(Option<String>),
+    SetEngineAnnotations(GraphicalAnnotations),
+    SetUserAnnotations(GraphicalAnnotations),
+    StartSession(PgnGame, SessionSettings),
+    SetPreferredLine(Option<NodeId>),
+    ExportDiagram(PathBuf),
+    ExportLineAnimation(PathBuf, AnimationSettings),
+    HistoryFirst,
+    HistoryBack,
+    HistoryForward,
+    HistoryLast,
+    GoToNode(NodeId),
+    MovePlayed {
+        uci: String,
+        san: String,
+        fen: String,
+    },
+    GameOver(Outcome),
+    PromotionRequested,
+    CurrentNodeChanged(Option<NodeId>),
+    PositionChanged(String),
+    EngineMoveRequested(String),
+    MoveGuessed(Guess),
+    UserAnnotationsChanged(Option<NodeId>, GraphicalAnnotations),
+    ExportFailed(String),
+    LineAnimationExportStarted,
+    LineAnimationExportFinished,
+    Internal(InternalMsg),
+}
+
+/// Messages the board sends to itself: parents can neither build nor inject them.
+pub struct InternalMsg(BoardEvent);
+
+enum BoardEvent {
+    HumanMovePlayed(PlayedMove),
+    PlayComputerMove(u32),
+    PreviewVariation(Option<usize>),
+    VariationChosen(usize),
+    VariationChooserClosed,
+    OpenPromotionChooser,
+    PromotionChosen(Role),
+    PromotionCancelled,
+    LineAnimationEncoded(Result<(), String>),
+}
+
+fn internal(event: BoardEvent) -> ChessBoardMsg {
+    ChessBoardMsg::Internal(InternalMsg(event))
 }
 
 #[widget]
 impl Widget for ChessBoard {
-    fn model(board_size: u32) -> ChessBoardModel {
+    fn model(relm: &Relm<Self>, board_size: u32) -> ChessBoardModel {
         let mut state_builder = ChessStateBuilder::new();
         state_builder.set_board_size(board_size);
         let chess_state = Rc::new(RefCell::new(state_builder.build()));
         let dnd_state = Rc::new(RefCell::new(DndState::default()));
+        let selection_state = Rc::new(RefCell::new(SelectionState::default()));
 
         ChessBoardModel {
+            relm: relm.clone(),
             chess_state,
             dnd_state,
+            selection_state,
+            computer_delay_ms: SessionSettings::default().computer_delay_ms,
+            session_generation: 0,
+            variation_chooser: None,
+            promotion_chooser: None,
+            animation_export: None,
         }
     }
 
@@ -179,6 +566,170 @@ impl Widget for ChessBoard {
                 }
                 self.repaint();
             }
+            ChessBoardMsg::SetPosition(position_fen) => {
+                if let Some(position) = position_from_fen(&position_fen) {
+                    {
+                        let mut chess_state = (*self.model.chess_state).borrow_mut();
+                        chess_state.board = position;
+                        chess_state.last_move = None;
+                        chess_state.pending_promotion = false;
+                        chess_state.engine_annotations = GraphicalAnnotations::default();
+                        chess_state.session = None;
+                        chess_state.game_over = chess_state.detect_outcome();
+                        chess_state.reset_history();
+                    }
+                    self.model.session_generation += 1;
+                    self.close_variation_chooser();
+                    self.close_promotion_chooser();
+                    set_selection_inactive(&self.model.selection_state);
+                    self.repaint();
+                    self.emit_position_changed();
+                }
+            }
+            ChessBoardMsg::PlayMove(move_uci) => {
+                let played = {
+                    let mut chess_state = (*self.model.chess_state).borrow_mut();
+                    let legal_move = Uci::from_ascii(move_uci.as_bytes())
+                        .ok()
+                        .and_then(|uci| uci.to_move(&chess_state.board).ok());
+                    legal_move.map(|legal_move| {
+                        let on_cursor = chess_state.current_node().is_some();
+                        if let Some(session) = chess_state.session.as_mut() {
+                            if !on_cursor || session.follow_move(&legal_move).is_none() {
+                                session.leave_book();
+                            }
+                        }
+                        chess_state.play_move(&legal_move)
+                    })
+                };
+                if let Some(played) = played {
+                    set_selection_inactive(&self.model.selection_state);
+                    self.repaint();
+                    self.on_move_played(played);
+                }
+            }
+            ChessBoardMsg::PlayEngineMove {
+                fen: position_fen,
+                uci,
+            } => {
+                let still_expected = {
+                    let chess_state = (*self.model.chess_state).borrow();
+                    fen(&chess_state.board) == position_fen && self.is_engine_turn()
+                };
+                if still_expected {
+                    self.update(ChessBoardMsg::PlayMove(uci));
+                }
+            }
+            ChessBoardMsg::SetInteractive(interactive) => {
+                (*self.model.chess_state).borrow_mut().interactive = interactive;
+            }
+            ChessBoardMsg::SetLastMove(move_uci) => {
+                {
+                    let mut chess_state = (*self.model.chess_state).borrow_mut();
+                    chess_state.last_move = move_uci
+                        .and_then(|move_uci| Uci::from_ascii(move_uci.as_bytes()).ok())
+                        .and_then(|uci| LastMove::from_uci(&uci));
+                }
+                self.repaint();
+            }
+            ChessBoardMsg::SetEngineAnnotations(annotations) => {
+                (*self.model.chess_state).borrow_mut().engine_annotations = annotations;
+                self.repaint();
+            }
+            ChessBoardMsg::SetUserAnnotations(annotations) => {
+                (*self.model.chess_state).borrow_mut().user_annotations = annotations;
+                self.repaint();
+            }
+            ChessBoardMsg::StartSession(game, settings) => {
+                {
+                    let mut chess_state_from_model = (*self.model.chess_state).borrow_mut();
+                    (*chess_state_from_model).board =
+                        game.tree.node(GameTree::ROOT).position.clone();
+                    (*chess_state_from_model).last_move = None;
+                    (*chess_state_from_model).pending_promotion = false;
+                    (*chess_state_from_model).engine_annotations = GraphicalAnnotations::default();
+                    (*chess_state_from_model).game_over = None;
+                    (*chess_state_from_model).session = Some(ReviserSession::new(
+                        game.tree,
+                        settings.white_player,
+                        settings.black_player,
+                    ));
+                    (*chess_state_from_model).engine_continuation = settings.engine_continuation;
+                    (*chess_state_from_model).reset_history();
+                }
+                self.model.computer_delay_ms = settings.computer_delay_ms;
+                self.model.session_generation += 1;
+                self.close_variation_chooser();
+                set_selection_inactive(&self.model.selection_state);
+                self.repaint();
+                self.emit_position_changed();
+                self.schedule_computer_move_if_needed();
+            }
+            ChessBoardMsg::SetPreferredLine(leaf) => {
+                if let Some(session) = (*self.model.chess_state).borrow_mut().session.as_mut() {
+                    session.set_preferred_line(leaf);
+                }
+            }
+            ChessBoardMsg::ExportDiagram(path) => {
+                let (position_fen, options) = {
+                    let chess_state = (*self.model.chess_state).borrow();
+                    (fen(&chess_state.board), chess_state.render_options())
+                };
+                if let Err(error) = save_diagram(&position_fen, &options, &path) {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(ChessBoardMsg::ExportFailed(error.to_string()));
+                }
+            }
+            ChessBoardMsg::ExportLineAnimation(path, settings) => {
+                let (frames, options) = {
+                    let chess_state = (*self.model.chess_state).borrow();
+                    let frames = match (&chess_state.session, chess_state.current_node()) {
+                        (Some(session), Some(node)) => Some(line_frames(session.tree(), node)),
+                        _ => None,
+                    };
+                    (frames, chess_state.render_options())
+                };
+                match frames {
+                    Some(frames) => self.encode_line_animation(frames, options, settings, path),
+                    None => self
+                        .model
+                        .relm
+                        .stream()
+                        .emit(ChessBoardMsg::ExportFailed(RenderError::NoLine.to_string())),
+                }
+            }
+            ChessBoardMsg::HistoryFirst => {
+                self.go_to_history_entry(|_, _| Some(0));
+            }
+            ChessBoardMsg::HistoryBack => {
+                self.go_to_history_entry(|index, _| index.checked_sub(1));
+            }
+            ChessBoardMsg::HistoryForward => {
+                self.go_to_history_entry(|index, _| Some(index + 1));
+            }
+            ChessBoardMsg::HistoryLast => {
+                self.go_to_history_entry(|_, length| length.checked_sub(1));
+            }
+            ChessBoardMsg::GoToNode(node) => {
+                (*self.model.chess_state)
+                    .borrow_mut()
+                    .rebuild_history_to_node(node);
+                self.go_to_history_entry(|_, length| length.checked_sub(1));
+            }
+            ChessBoardMsg::Internal(InternalMsg(event)) => self.update_internal(event),
+            ChessBoardMsg::MovePlayed { .. }
+            | ChessBoardMsg::GameOver(_)
+            | ChessBoardMsg::PromotionRequested
+            | ChessBoardMsg::CurrentNodeChanged(_)
+            | ChessBoardMsg::PositionChanged(_)
+            | ChessBoardMsg::EngineMoveRequested(_)
+            | ChessBoardMsg::MoveGuessed(_)
+            | ChessBoardMsg::UserAnnotationsChanged(_, _)
+            | ChessBoardMsg::ExportFailed(_)
+            | ChessBoardMsg::LineAnimationExportStarted
+            | ChessBoardMsg::LineAnimationExportFinished => {}
         }
     }
 
@@ -196,6 +747,112 @@ impl Widget for ChessBoard {
 }
 
 impl ChessBoard {
+    fn update_internal(&mut self, event: BoardEvent) {
+        match event {
+            BoardEvent::HumanMovePlayed(played) => {
+                self.on_move_played(played);
+            }
+            BoardEvent::PlayComputerMove(generation) => {
+                if generation == self.model.session_generation {
+                    if self.computer_continuations_count() > 1 {
+                        self.open_variation_chooser();
+                    } else if let Some(played) = self.play_computer_move(0) {
+                        self.repaint();
+                        self.on_move_played(played);
+                    }
+                }
+            }
+            BoardEvent::PreviewVariation(child_index) => {
+                {
+                    let mut chess_state = (*self.model.chess_state).borrow_mut();
+                    let preview = child_index.and_then(|child_index| {
+                        let session = chess_state.session.as_ref()?;
+                        let tree = session.tree();
+                        let child = *tree.node(session.cursor()).children.get(child_index)?;
+                        tree.node(child)
+                            .move_played
+                            .as_ref()
+                            .and_then(LastMove::from_move)
+                    });
+                    chess_state.variation_preview = preview;
+                }
+                self.repaint();
+            }
+            BoardEvent::VariationChosen(child_index) => {
+                self.close_variation_chooser();
+                if let Some(played) = self.play_computer_move(child_index) {
+                    self.repaint();
+                    self.on_move_played(played);
+                }
+            }
+            BoardEvent::VariationChooserClosed => {
+                // Dismissed without a choice: the suggested line, or else the main line, is
+                // played so that the session goes on. A destroyed chooser is no longer stored.
+                let dismissed = self
+                    .model
+                    .variation_chooser
+                    .as_ref()
+                    .map_or(false, |popover| !popover.get_visible());
+                if dismissed {
+                    let child_index = self.preferred_child_index().unwrap_or(0);
+                    self.update_internal(BoardEvent::VariationChosen(child_index));
+                }
+            }
+            BoardEvent::OpenPromotionChooser => {
+                self.open_promotion_chooser();
+                self.model
+                    .relm
+                    .stream()
+                    .emit(ChessBoardMsg::PromotionRequested);
+            }
+            BoardEvent::PromotionChosen(role) => {
+                let origin = dnd_origin(&self.model.dnd_state);
+                let target = dnd_target(&self.model.dnd_state);
+                set_pending_promotion_inactive(&self.model.chess_state);
+                self.close_promotion_chooser();
+
+                let attempt =
+                    try_to_apply_move(origin, target, Some(role), &self.model.chess_state);
+                self.repaint();
+                match attempt {
+                    MoveAttempt::Played(played, guess) => {
+                        if let Some(guess) = guess {
+                            self.model
+                                .relm
+                                .stream()
+                                .emit(ChessBoardMsg::MoveGuessed(guess));
+                        }
+                        self.on_move_played(played);
+                    }
+                    MoveAttempt::Rejected(guess) => self
+                        .model
+                        .relm
+                        .stream()
+                        .emit(ChessBoardMsg::MoveGuessed(guess)),
+                    MoveAttempt::Ignored => {}
+                }
+            }
+            BoardEvent::PromotionCancelled => {
+                set_pending_promotion_inactive(&self.model.chess_state);
+                self.close_promotion_chooser();
+                self.repaint();
+            }
+            BoardEvent::LineAnimationEncoded(result) => {
+                self.model.animation_export = None;
+                self.model
+                    .relm
+                    .stream()
+                    .emit(ChessBoardMsg::LineAnimationExportFinished);
+                if let Err(message) = result {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(ChessBoardMsg::ExportFailed(message));
+                }
+            }
+        }
+    }
+
     pub fn repaint(&self) {
         let chess_state = (*self.model.chess_state).borrow();
         let size = chess_state.size;
@@ -209,6 +866,339 @@ impl ChessBoard {
             }));
     }
 
+    fn go_to_history_entry<F>(&mut self, target_index: F)
+    where
+        F: Fn(usize, usize) -> Option<usize>,
+    {
+        let (moved, at_history_end) = {
+            let mut chess_state = (*self.model.chess_state).borrow_mut();
+            let index = target_index(chess_state.history_index, chess_state.history.len());
+            let moved = index.map_or(false, |index| chess_state.go_to_history_entry(index));
+            (moved, chess_state.is_at_history_end())
+        };
+
+        if moved {
+            self.model.session_generation += 1;
+            self.close_variation_chooser();
+            self.close_promotion_chooser();
+            set_selection_inactive(&self.model.selection_state);
+            self.repaint();
+            self.emit_position_changed();
+            if at_history_end {
+                self.schedule_computer_move_if_needed();
+            }
+        }
+    }
+
+    fn emit_position_changed(&self) {
+        let (current_node, position_fen) = {
+            let mut chess_state = (*self.model.chess_state).borrow_mut();
+            chess_state.position_shown_at = Instant::now();
+            (chess_state.current_node(), fen(&chess_state.board))
+        };
+        self.model
+            .relm
+            .stream()
+            .emit(ChessBoardMsg::CurrentNodeChanged(current_node));
+        self.model
+            .relm
+            .stream()
+            .emit(ChessBoardMsg::PositionChanged(position_fen));
+    }
+
+    // Encoding takes seconds for a long line: it is done away from the gtk thread.
+    fn encode_line_animation(
+        &mut self,
+        frames: Vec<AnimationFrame>,
+        options: RenderOptions,
+        settings: AnimationSettings,
+        path: PathBuf,
+    ) {
+        if self.model.animation_export.is_some() {
+            self.model.relm.stream().emit(ChessBoardMsg::ExportFailed(
+                "An animation is already being exported".to_string(),
+            ));
+            return;
+        }
+
+        let stream = self.model.relm.stream().clone();
+        let (channel, sender) = Channel::new(move |result| {
+            stream.emit(internal(BoardEvent::LineAnimationEncoded(result)))
+        });
+        thread::spawn(move || {
+            let result =
+                save_gif(&frames, &options, settings, &path).map_err(|err| err.to_string());
+            // The board may be gone already, nobody is waiting for the result then.
+            let _ = sender.send(result);
+        });
+        self.model.animation_export = Some(channel);
+        self.model
+            .relm
+            .stream()
+            .emit(ChessBoardMsg::LineAnimationExportStarted);
+    }
+
+    fn on_move_played(&self, played: PlayedMove) {
+        self.model.relm.stream().emit(ChessBoardMsg::MovePlayed {
+            uci: played.uci,
+            san: played.san,
+            fen: played.fen,
+        });
+        self.emit_position_changed();
+
+        let outcome = {
+            let mut chess_state = (*self.model.chess_state).borrow_mut();
+            chess_state.game_over = chess_state.detect_outcome();
+            chess_state.game_over
+        };
+        match outcome {
+            Some(outcome) => self
+                .model
+                .relm
+                .stream()
+                .emit(ChessBoardMsg::GameOver(outcome)),
+            None => self.schedule_computer_move_if_needed(),
+        }
+    }
+
+    fn is_computer_turn(&self) -> bool {
+        let chess_state = (*self.model.chess_state).borrow();
+        match &chess_state.session {
+            Some(session) => {
+                chess_state.current_node().is_some()
+                    && session.player_to_move() == PlayerKind::Computer
+                    && !session.is_line_finished()
+            }
+            None => false,
+        }
+    }
+
+    fn is_engine_turn(&self) -> bool {
+        let chess_state = (*self.model.chess_state).borrow();
+        chess_state.engine_continuation
+            && chess_state.game_over.is_none()
+            && chess_state.is_out_of_book()
+            && chess_state.player_to_move() == Some(PlayerKind::Computer)
+    }
+
+    fn schedule_computer_move_if_needed(&self) {
+        if self.is_computer_turn() {
+            let generation = self.model.session_generation;
+            timeout(
+                self.model.relm.stream(),
+                self.model.computer_delay_ms,
+                move || internal(BoardEvent::PlayComputerMove(generation)),
+            );
+        } else if self.is_engine_turn() {
+            let position_fen = fen(&(*self.model.chess_state).borrow().board);
+            self.model
+                .relm
+                .stream()
+                .emit(ChessBoardMsg::EngineMoveRequested(position_fen));
+        }
+    }
+
+    fn preferred_child_index(&self) -> Option<usize> {
+        let chess_state = (*self.model.chess_state).borrow();
+        chess_state
+            .session
+            .as_ref()
+            .and_then(|session| session.preferred_child_index())
+    }
+
+    fn computer_continuations_count(&self) -> usize {
+        let chess_state = (*self.model.chess_state).borrow();
+        chess_state
+            .session
+            .as_ref()
+            .map_or(0, |session| session.expected_moves().len())
+    }
+
+    // The line to revise, if any, is only suggested: the user still picks the move.
+    fn open_variation_chooser(&mut self) {
+        self.close_variation_chooser();
+
+        let preferred_child = self.preferred_child_index();
+        let entries = {
+            let chess_state = (*self.model.chess_state).borrow();
+            match &chess_state.session {
+                Some(session) => {
+                    let tree = session.tree();
+                    tree.node(session.cursor())
+                        .children
+                        .iter()
+                        .map(|child| {
+                            let node = tree.node(*child);
+                            let comment = node
+                                .comment_after
+                                .as_ref()
+                                .or_else(|| node.comment_before.as_ref());
+                            match comment {
+                                Some(comment) => format!("{}  {}", node.san, comment),
+                                None => node.san.clone(),
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                }
+                None => vec![],
+            }
+        };
+
+        let popover = gtk::Popover::new(Some(&self.canvas));
+        popover.set_modal(false);
+        popover.set_position(gtk::PositionType::Right);
+
+        let entries_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        entries_box.set_border_width(5);
+        entries_box.add(&gtk::Label::new(Some("Choose the computer move")));
+
+        let mut preferred_button = None;
+        for (child_index, entry) in entries.iter().enumerate() {
+            let entry = if preferred_child == Some(child_index) {
+                format!("{}  (line to revise)", entry)
+            } else {
+                entry.clone()
+            };
+            let label = gtk::Label::new(Some(entry.as_str()));
+            label.set_line_wrap(true);
+            label.set_max_width_chars(40);
+            label.set_xalign(0.0);
+
+            let button = gtk::Button::new();
+            button.add(&label);
+
+            let stream = self.model.relm.stream().clone();
+            button.connect_clicked(move |_| {
+                stream.emit(internal(BoardEvent::VariationChosen(child_index)));
+            });
+            let stream = self.model.relm.stream().clone();
+            button.connect_enter_notify_event(move |_, _| {
+                stream.emit(internal(BoardEvent::PreviewVariation(Some(child_index))));
+                Inhibit(false)
+            });
+            let stream = self.model.relm.stream().clone();
+            button.connect_leave_notify_event(move |_, _| {
+                stream.emit(internal(BoardEvent::PreviewVariation(None)));
+                Inhibit(false)
+            });
+
+            entries_box.add(&button);
+            if preferred_child == Some(child_index) {
+                preferred_button = Some(button);
+            }
+        }
+
+        let stream = self.model.relm.stream().clone();
+        popover.connect_closed(move |_| {
+            stream.emit(internal(BoardEvent::VariationChooserClosed));
+        });
+
+        popover.add(&entries_box);
+        popover.show_all();
+        if let Some(button) = preferred_button {
+            button.grab_focus();
+            self.model
+                .relm
+                .stream()
+                .emit(internal(BoardEvent::PreviewVariation(preferred_child)));
+        }
+        self.model.variation_chooser = Some(popover);
+    }
+
+    fn close_variation_chooser(&mut self) {
+        if let Some(popover) = self.model.variation_chooser.take() {
+            popover.destroy();
+        }
+        (*self.model.chess_state).borrow_mut().variation_preview = None;
+    }
+
+    fn open_promotion_chooser(&mut self) {
+        self.close_promotion_chooser();
+
+        let popover = gtk::Popover::new(Some(&self.canvas));
+        popover.set_pointing_to(&self.target_cell_rectangle());
+
+        let roles_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        roles_box.set_border_width(5);
+        let roles = [
+            (Role::Queen, "Queen"),
+            (Role::Rook, "Rook"),
+            (Role::Bishop, "Bishop"),
+            (Role::Knight, "Knight"),
+        ];
+        for (role, label) in roles.iter() {
+            let role = *role;
+            let button = gtk::Button::new_with_label(label);
+            let stream = self.model.relm.stream().clone();
+            button.connect_clicked(move |_| {
+                stream.emit(internal(BoardEvent::PromotionChosen(role)));
+            });
+            roles_box.add(&button);
+        }
+
+        let cancel_button = gtk::Button::new_with_label("Cancel");
+        let stream = self.model.relm.stream().clone();
+        cancel_button.connect_clicked(move |_| {
+            stream.emit(internal(BoardEvent::PromotionCancelled));
+        });
+        roles_box.add(&cancel_button);
+
+        let weak_chess_state = Rc::downgrade(&self.model.chess_state);
+        let stream = self.model.relm.stream().clone();
+        popover.connect_closed(move |_| {
+            if let Some(chess_state) = weak_chess_state.upgrade() {
+                if chess_state.borrow().pending_promotion {
+                    stream.emit(internal(BoardEvent::PromotionCancelled));
+                }
+            }
+        });
+
+        popover.add(&roles_box);
+        popover.show_all();
+        self.model.promotion_chooser = Some(popover);
+    }
+
+    fn close_promotion_chooser(&mut self) {
+        if let Some(popover) = self.model.promotion_chooser.take() {
+            popover.destroy();
+        }
+    }
+
+    fn target_cell_rectangle(&self) -> gdk::Rectangle {
+        let chess_state = (*self.model.chess_state).borrow();
+        let target = dnd_target(&self.model.dnd_state);
+        let cells_size = chess_state.size as f64 / 9_f64;
+
+        let col = if chess_state.black_side == BlackSide::BlackBottom {
+            7 - target.file
+        } else {
+            target.file
+        };
+        let row = if chess_state.black_side == BlackSide::BlackBottom {
+            target.rank
+        } else {
+            7 - target.rank
+        };
+
+        gdk::Rectangle {
+            x: (cells_size * (0.5 + col as f64)) as i32,
+            y: (cells_size * (0.5 + row as f64)) as i32,
+            width: cells_size as i32,
+            height: cells_size as i32,
+        }
+    }
+
+    fn play_computer_move(&self, child_index: usize) -> Option<PlayedMove> {
+        let mut chess_state = (*self.model.chess_state).borrow_mut();
+        let computer_move = {
+            let session = chess_state.session.as_mut()?;
+            let node_id = session.advance_to(child_index)?;
+            session.tree().node(node_id).move_played.clone()?
+        };
+
+        Some(chess_state.play_move(&computer_move))
+    }
+
     pub fn set_canvas_size(&self) {
         let chess_state = (*self.model.chess_state).borrow();
         let size = chess_state.size;
@@ -229,12 +1219,16 @@ impl ChessBoard {
         {
             let weak_chess_state = Rc::downgrade(&self.model.chess_state);
             let weak_dnd_state = Rc::downgrade(&self.model.dnd_state);
+            let weak_selection_state = Rc::downgrade(&self.model.selection_state);
             self.canvas.connect_draw(move |_source, context| {
                 if let Some(chess_state) = weak_chess_state.upgrade() {
                     if let Some(dnd_state) = weak_dnd_state.upgrade() {
-                        let chess_state = chess_state.borrow();
-                        let dnd_state = dnd_state.borrow();
-                        painter.paint(&context, &chess_state, &dnd_state);
+                        if let Some(selection_state) = weak_selection_state.upgrade() {
+                            let chess_state = chess_state.borrow();
+                            let dnd_state = dnd_state.borrow();
+                            let selection_state = selection_state.borrow();
+                            painter.paint(&context, &chess_state, &dnd_state, &selection_state);
+                        }
                     }
                 }
 
@@ -261,14 +1255,26 @@ impl ChessBoard {
     fn add_canvas_mouse_press_implementation(&self) {
         let weak_chess_state = Rc::downgrade(&self.model.chess_state);
         let weak_dnd_state = Rc::downgrade(&self.model.dnd_state);
+        let weak_selection_state = Rc::downgrade(&self.model.selection_state);
+        let stream = self.model.relm.stream().clone();
 
         self.canvas
             .connect_button_press_event(move |canvas, event| {
                 if let Some(dnd_state) = weak_dnd_state.upgrade() {
                     if let Some(chess_state) = weak_chess_state.upgrade() {
-                        let dnd_state = &(*dnd_state);
-                        let chess_state = &(*chess_state);
-                        mouse_pressed_handler(dnd_state, chess_state, canvas, event);
+                        if let Some(selection_state) = weak_selection_state.upgrade() {
+                            let dnd_state = &(*dnd_state);
+                            let selection_state = &(*selection_state);
+                            let chess_state = &(*chess_state);
+                            let outcome = mouse_pressed_handler(
+                                dnd_state,
+                                selection_state,
+                                chess_state,
+                                canvas,
+                                event,
+                            );
+                            emit_drop_outcome(&stream, chess_state, outcome);
+                        }
                     }
                 }
                 Inhibit(false)
@@ -278,14 +1284,26 @@ impl ChessBoard {
     fn add_canvas_mouse_release_implementation(&self) {
         let weak_chess_state = Rc::downgrade(&self.model.chess_state);
         let weak_dnd_state = Rc::downgrade(&self.model.dnd_state);
+        let weak_selection_state = Rc::downgrade(&self.model.selection_state);
+        let stream = self.model.relm.stream().clone();
 
         self.canvas
             .connect_button_release_event(move |canvas, event| {
                 if let Some(dnd_state) = weak_dnd_state.upgrade() {
                     if let Some(chess_state) = weak_chess_state.upgrade() {
-                        let dnd_state = &(*dnd_state);
-                        let chess_state = &(*chess_state);
-                        mouse_released_handler(dnd_state, chess_state, canvas, event);
+                        if let Some(selection_state) = weak_selection_state.upgrade() {
+                            let dnd_state = &(*dnd_state);
+                            let selection_state = &(*selection_state);
+                            let chess_state = &(*chess_state);
+                            let outcome = mouse_released_handler(
+                                dnd_state,
+                                selection_state,
+                                chess_state,
+                                canvas,
+                                event,
+                            );
+                            emit_drop_outcome(&stream, chess_state, outcome);
+                        }
                     }
                 }
                 Inhibit(false)
@@ -309,3 +1327,28 @@ impl ChessBoard {
             });
     }
 }
+
+fn emit_drop_outcome(
+    stream: &EventStream<ChessBoardMsg>,
+    chess_state: &RefCell<ChessState>,
+    outcome: DropOutcome,
+) {
+    match outcome {
+        DropOutcome::AnnotationsChanged => {
+            let chess_state = chess_state.borrow();
+            stream.emit(ChessBoardMsg::UserAnnotationsChanged(
+                chess_state.current_node(),
+                chess_state.user_annotations.clone(),
+            ));
+        }
+        DropOutcome::MovePlayed(played, guess) => {
+            if let Some(guess) = guess {
+                stream.emit(ChessBoardMsg::MoveGuessed(guess));
+            }
+            stream.emit(internal(BoardEvent::HumanMovePlayed(played)));
+        }
+        DropOutcome::GuessRejected(guess) => stream.emit(ChessBoardMsg::MoveGuessed(guess)),
+        DropOutcome::PromotionRequested => stream.emit(internal(BoardEvent::OpenPromotionChooser)),
+        DropOutcome::Ignored => {}
+    }
+}
@@ -1,20 +1,44 @@
-use gdk::{EventButton, EventMotion};
+use gdk::{EventButton, EventMotion, ModifierType};
 use gtk::prelude::*;
 use gtk::DrawingArea;
-use shakmaty::{uci::Uci, Position, Setup};
+use shakmaty::{uci::Uci, Color, Move, Position, Role, Setup, Square};
 use std::cell::RefCell;
 
 use std::cmp;
 
 use super::chess_board_widget::*;
+use crate::pgn::AnnotationColor;
+use crate::reviser::{PlayerKind, Verdict};
+
+const MOVE_BUTTON: u32 = 1;
+const ANNOTATION_BUTTON: u32 = 3;
+
+pub enum DropOutcome {
+    Ignored,
+    MovePlayed(PlayedMove, Option<Guess>),
+    GuessRejected(Guess),
+    PromotionRequested,
+    AnnotationsChanged,
+}
 
 pub fn mouse_pressed_handler(
     dnd_state: &RefCell<DndState>,
+    selection_state: &RefCell<SelectionState>,
     chess_state: &RefCell<ChessState>,
     canvas: &DrawingArea,
     event: &EventButton,
-) {
-    if !dnd_is_active(dnd_state) && !is_pending_promotion(chess_state) {
+) -> DropOutcome {
+    if event.get_button() == ANNOTATION_BUTTON {
+        annotation_pressed_handler(dnd_state, chess_state, event);
+        return DropOutcome::Ignored;
+    }
+
+    let mut outcome = DropOutcome::Ignored;
+    if event.get_button() == MOVE_BUTTON
+        && !dnd_is_active(dnd_state)
+        && !is_pending_promotion(chess_state)
+        && is_interactive(chess_state)
+    {
         let (x, y) = event.get_position();
         let file = get_file(x, chess_state);
         let rank = get_rank(y, chess_state);
@@ -22,24 +46,45 @@ pub fn mouse_pressed_handler(
             update_cursor_position(x, y, chess_state, dnd_state);
             update_target_coordinates(x, y, chess_state, dnd_state);
 
-            let file = file as u8;
-            let rank = rank as u8;
-
-            if let Some(board_fen) = piece_at_square(file, rank, chess_state) {
-                set_dnd_active(board_fen, file, rank, dnd_state);
-                repaint_canvas(canvas, chess_state);
+            let piece = piece_at_square(file as u8, rank as u8, chess_state);
+            let own_piece = piece.map_or(false, |piece| is_own_piece(piece, chess_state));
+
+            if selection_is_active(selection_state) && !own_piece {
+                let selected = selected_cell(selection_state);
+                set_selection_inactive(selection_state);
+                if let Some(selected_fen) =
+                    piece_at_square(selected.file, selected.rank, chess_state)
+                {
+                    set_dnd_origin(selected_fen, selected.file, selected.rank, dnd_state);
+                    outcome = play_or_request_promotion(file, rank, chess_state, dnd_state);
+                }
+            } else if let Some(board_fen) = piece {
+                set_selection_inactive(selection_state);
+                set_dnd_active(board_fen, file as u8, rank as u8, dnd_state);
             }
+            repaint_canvas(canvas, chess_state);
         }
     }
+
+    outcome
 }
 
 pub fn mouse_released_handler(
     dnd_state: &RefCell<DndState>,
+    selection_state: &RefCell<SelectionState>,
     chess_state: &RefCell<ChessState>,
     canvas: &DrawingArea,
     event: &EventButton,
-) {
-    if dnd_is_active(dnd_state) && !is_pending_promotion(chess_state) {
+) -> DropOutcome {
+    if event.get_button() == ANNOTATION_BUTTON {
+        return annotation_released_handler(dnd_state, chess_state, canvas, event);
+    }
+
+    let mut outcome = DropOutcome::Ignored;
+    if event.get_button() == MOVE_BUTTON
+        && dnd_is_active(dnd_state)
+        && !is_pending_promotion(chess_state)
+    {
         set_dnd_inactive(dnd_state);
         let (x, y) = event.get_position();
         update_cursor_position(x, y, chess_state, dnd_state);
@@ -47,13 +92,111 @@ pub fn mouse_released_handler(
         let file = get_file(x, chess_state);
         let rank = get_rank(y, chess_state);
         if cell_in_bounds(file, rank) {
-            if is_promotion_move(rank, chess_state, dnd_state) {
-                set_pending_promotion_active(chess_state);
-                repaint_canvas(canvas, chess_state);
+            let origin = dnd_origin(dnd_state);
+            let released_on_origin = origin.file == file as u8 && origin.rank == rank as u8;
+            if released_on_origin {
+                let moved_piece = dnd_state.borrow().moved_piece_fen;
+                if is_own_piece(moved_piece, chess_state) {
+                    set_selection_active(origin, selection_state);
+                }
             } else {
-                try_to_apply_move(x, y, chess_state, dnd_state);
-                repaint_canvas(canvas, chess_state);
+                outcome = play_or_request_promotion(file, rank, chess_state, dnd_state);
+            }
+        }
+        repaint_canvas(canvas, chess_state);
+    }
+
+    outcome
+}
+
+fn annotation_pressed_handler(
+    dnd_state: &RefCell<DndState>,
+    chess_state: &RefCell<ChessState>,
+    event: &EventButton,
+) {
+    let (x, y) = event.get_position();
+    let file = get_file(x, chess_state);
+    let rank = get_rank(y, chess_state);
+
+    let mut dnd_state = dnd_state.borrow_mut();
+    dnd_state.annotation_origin = if cell_in_bounds(file, rank) {
+        Some(BoardCellCoord {
+            file: file as u8,
+            rank: rank as u8,
+        })
+    } else {
+        None
+    };
+}
+
+fn annotation_released_handler(
+    dnd_state: &RefCell<DndState>,
+    chess_state: &RefCell<ChessState>,
+    canvas: &DrawingArea,
+    event: &EventButton,
+) -> DropOutcome {
+    let origin = dnd_state.borrow_mut().annotation_origin.take();
+    let (x, y) = event.get_position();
+    let file = get_file(x, chess_state);
+    let rank = get_rank(y, chess_state);
+
+    if let Some(origin) = origin {
+        if cell_in_bounds(file, rank) {
+            let origin_square = cell_to_square(origin.file, origin.rank);
+            let target_square = cell_to_square(file as u8, rank as u8);
+            let color = annotation_color_for(event);
+            {
+                let mut chess_state = chess_state.borrow_mut();
+                if origin_square == target_square {
+                    chess_state
+                        .user_annotations
+                        .toggle_square(origin_square, color);
+                } else {
+                    chess_state
+                        .user_annotations
+                        .toggle_arrow(origin_square, target_square, color);
+                }
             }
+            repaint_canvas(canvas, chess_state);
+            return DropOutcome::AnnotationsChanged;
+        }
+    }
+
+    DropOutcome::Ignored
+}
+
+fn annotation_color_for(event: &EventButton) -> AnnotationColor {
+    let modifiers = event.get_state();
+    let shift = modifiers.contains(ModifierType::SHIFT_MASK);
+    let alt = modifiers.contains(ModifierType::MOD1_MASK);
+
+    match (shift, alt) {
+        (true, true) => AnnotationColor::Yellow,
+        (true, false) => AnnotationColor::Red,
+        (false, true) => AnnotationColor::Blue,
+        (false, false) => AnnotationColor::Green,
+    }
+}
+
+fn play_or_request_promotion(
+    file: i8,
+    rank: i8,
+    chess_state: &RefCell<ChessState>,
+    dnd_state: &RefCell<DndState>,
+) -> DropOutcome {
+    let target = BoardCellCoord {
+        file: file as u8,
+        rank: rank as u8,
+    };
+    if is_promotion_move(target, chess_state, dnd_state) {
+        set_pending_promotion_active(chess_state);
+        DropOutcome::PromotionRequested
+    } else {
+        let origin = dnd_origin(dnd_state);
+        match try_to_apply_move(origin, target, None, chess_state) {
+            MoveAttempt::Played(played, guess) => DropOutcome::MovePlayed(played, guess),
+            MoveAttempt::Rejected(guess) => DropOutcome::GuessRejected(guess),
+            MoveAttempt::Ignored => DropOutcome::Ignored,
         }
     }
 }
@@ -139,12 +282,48 @@ fn update_target_coordinates(
 }
 
 fn set_dnd_active(value: char, origin_file: u8, origin_rank: u8, dnd_state: &RefCell<DndState>) {
+    set_dnd_origin(value, origin_file, origin_rank, dnd_state);
+
+    let mut dnd_state = dnd_state.borrow_mut();
+    dnd_state.dnd_active = true;
+}
+
+fn set_dnd_origin(value: char, origin_file: u8, origin_rank: u8, dnd_state: &RefCell<DndState>) {
     let mut dnd_state = dnd_state.borrow_mut();
     dnd_state.origin_file = origin_file;
     dnd_state.origin_rank = origin_rank;
 
     dnd_state.moved_piece_fen = value;
-    dnd_state.dnd_active = true;
+}
+
+fn selection_is_active(selection_state: &RefCell<SelectionState>) -> bool {
+    let selection_state = selection_state.borrow();
+    selection_state.selection_active
+}
+
+fn selected_cell(selection_state: &RefCell<SelectionState>) -> BoardCellCoord {
+    let selection_state = selection_state.borrow();
+    BoardCellCoord {
+        file: selection_state.file,
+        rank: selection_state.rank,
+    }
+}
+
+fn set_selection_active(cell: BoardCellCoord, selection_state: &RefCell<SelectionState>) {
+    let mut selection_state = selection_state.borrow_mut();
+    selection_state.file = cell.file;
+    selection_state.rank = cell.rank;
+    selection_state.selection_active = true;
+}
+
+pub fn set_selection_inactive(selection_state: &RefCell<SelectionState>) {
+    let mut selection_state = selection_state.borrow_mut();
+    selection_state.selection_active = false;
+}
+
+fn is_own_piece(piece_fen: char, chess_state: &RefCell<ChessState>) -> bool {
+    let chess_state = chess_state.borrow();
+    piece_fen.is_ascii_uppercase() == (chess_state.board.turn() == Color::White)
 }
 
 fn set_dnd_inactive(dnd_state: &RefCell<DndState>) {
@@ -225,58 +404,97 @@ fn cell_to_uci<'a>(file: u8, rank: u8) -> String {
     format!("{}{}", file_ascii as char, rank_ascii as char)
 }
 
-fn try_to_apply_move(
-    x: f64,
-    y: f64,
-    chess_state: &RefCell<ChessState>,
-    dnd_state: &RefCell<DndState>,
-) {
-    let dnd_state = dnd_state.borrow();
-
-    let target_file = get_file(x, chess_state);
-    let target_rank = get_rank(y, chess_state);
+fn cell_to_square(file: u8, rank: u8) -> Square {
+    cell_to_uci(file, rank)
+        .parse::<Square>()
+        .expect("invalid cell coordinates")
+}
 
-    let origin_cell_uci = cell_to_uci(dnd_state.origin_file, dnd_state.origin_rank);
-    let target_cell_uci = cell_to_uci(target_file as u8, target_rank as u8);
+pub fn try_to_apply_move(
+    origin: BoardCellCoord,
+    target: BoardCellCoord,
+    promotion: Option<Role>,
+    chess_state: &RefCell<ChessState>,
+) -> MoveAttempt {
+    let origin_cell_uci = cell_to_uci(origin.file, origin.rank);
+    let target_cell_uci = cell_to_uci(target.file, target.rank);
+    let promotion_uci = promotion.map_or(String::new(), |role| role.char().to_string());
 
-    let move_uci = format!("{}{}", origin_cell_uci, target_cell_uci,);
+    let move_uci = format!("{}{}{}", origin_cell_uci, target_cell_uci, promotion_uci);
     let move_uci = move_uci.as_str();
     let move_uci = Uci::from_ascii(move_uci.as_bytes());
 
     if let Ok(legal_move) = move_uci {
         let mut chess_state = chess_state.borrow_mut();
         if let Ok(move_conversion) = legal_move.to_move(&chess_state.board) {
-            let move_result = chess_state.board.clone().play(&move_conversion);
-            let move_applied = move_result.is_ok();
-
-            if move_applied {
-                let last_move = LastMove {
-                    origin: BoardCellCoord {
-                        file: dnd_state.origin_file,
-                        rank: dnd_state.origin_rank,
-                    },
-                    target: BoardCellCoord {
-                        file: target_file as u8,
-                        rank: target_rank as u8,
-                    },
-                };
-
-                chess_state.last_move = Some(last_move);
-                chess_state.board = move_result.unwrap();
+            let guess = chess_state.guess(&move_conversion);
+            if accepted_by_session(&mut chess_state, &move_conversion) {
+                return MoveAttempt::Played(chess_state.play_move(&move_conversion), guess);
+            }
+            if let Some(guess) = guess {
+                return MoveAttempt::Rejected(guess);
             }
         }
     }
+
+    MoveAttempt::Ignored
+}
+
+pub fn dnd_origin(dnd_state: &RefCell<DndState>) -> BoardCellCoord {
+    let dnd_state = dnd_state.borrow();
+    BoardCellCoord {
+        file: dnd_state.origin_file,
+        rank: dnd_state.origin_rank,
+    }
+}
+
+pub fn dnd_target(dnd_state: &RefCell<DndState>) -> BoardCellCoord {
+    let dnd_state = dnd_state.borrow();
+    BoardCellCoord {
+        file: dnd_state.target_file,
+        rank: dnd_state.target_rank,
+    }
+}
+
+fn accepted_by_session(chess_state: &mut ChessState, played_move: &Move) -> bool {
+    if chess_state.engine_continuation && chess_state.is_out_of_book() {
+        let accepted = chess_state.player_to_move() == Some(PlayerKind::Human);
+        if let (true, Some(session)) = (accepted, chess_state.session.as_mut()) {
+            session.leave_book();
+        }
+        return accepted;
+    }
+
+    match chess_state.session.as_mut() {
+        Some(session) => match session.submit_move(played_move) {
+            Some(Verdict::Wrong) | None => false,
+            Some(_) => {
+                session.advance();
+                true
+            }
+        },
+        None => true,
+    }
 }
 
+// Only a legal promotion opens the piece chooser.
 fn is_promotion_move(
-    target_rank: i8,
+    target: BoardCellCoord,
     chess_state: &RefCell<ChessState>,
     dnd_state: &RefCell<DndState>,
 ) -> bool {
-    let dnd_state = dnd_state.borrow();
-    let moving_piece = piece_at_square(dnd_state.origin_file, dnd_state.origin_rank, chess_state)
-        .expect("Could not get moved piece");
-    (moving_piece == 'P' && target_rank == 7) || (moving_piece == 'p' && target_rank == 0)
+    let origin = dnd_origin(dnd_state);
+    let chess_state = chess_state.borrow();
+    chess_state.board.legal_moves().iter().any(|legal_move| {
+        legal_move.promotion().is_some()
+            && legal_move.from().map(BoardCellCoord::from_square) == Some(origin)
+            && BoardCellCoord::from_square(legal_move.to()) == target
+    })
+}
+
+fn is_interactive(chess_state: &RefCell<ChessState>) -> bool {
+    let chess_state = chess_state.borrow();
+    chess_state.interactive && chess_state.game_over.is_none()
 }
 
 fn is_pending_promotion(chess_state: &RefCell<ChessState>) -> bool {
@@ -288,3 +506,8 @@ fn set_pending_promotion_active(chess_state: &RefCell<ChessState>) {
     let mut chess_state = chess_state.borrow_mut();
     chess_state.pending_promotion = true;
 }
+
+pub fn set_pending_promotion_inactive(chess_state: &RefCell<ChessState>) {
+    let mut chess_state = chess_state.borrow_mut();
+    chess_state.pending_promotion = false;
+}
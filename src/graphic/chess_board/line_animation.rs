@@ -0,0 +1,268 @@
+use cairo::{Context, Format, ImageSurface};
+use gif::SetParameter;
+use shakmaty::fen::fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{Color, Setup};
+
+use std::fs::File;
+use std::path::Path;
+
+use super::chess_board_widget::LastMove;
+use super::offscreen_rendering::{render_position, RenderError, RenderOptions};
+use crate::pgn::{GameTree, GraphicalAnnotations, NodeId};
+
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+// Each frame is rendered then quantized in memory: larger boards make huge files.
+const GIF_MAX_BOARD_SIZE: u32 = 2048;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AnimationSettings {
+    pub delay_ms: u32,
+    pub captions: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            delay_ms: 1000,
+            captions: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub fen: String,
+    pub last_move: Option<LastMove>,
+    pub caption: Option<String>,
+}
+
+/// Frames from the start position to the end of the line going through the node.
+pub fn line_frames(tree: &GameTree, node: NodeId) -> Vec<AnimationFrame> {
+    let line = tree
+        .path_to(node)
+        .into_iter()
+        .chain(tree.main_line_from(node))
+        .collect::<Vec<_>>();
+    let start_frame = AnimationFrame {
+        fen: fen(&tree.node(GameTree::ROOT).position),
+        last_move: None,
+        caption: None,
+    };
+
+    std::iter::once(start_frame)
+        .chain(line.into_iter().filter_map(|id| {
+            let move_node = tree.node(id);
+            let played_move = move_node.move_played.as_ref()?;
+            let previous_position = &tree.node(move_node.parent?).position;
+            let san = SanPlus::from_move(previous_position.clone(), played_move);
+            let caption = match previous_position.turn() {
+                Color::White => format!("{}. {}", previous_position.fullmoves(), san),
+                Color::Black => format!("{}... {}", previous_position.fullmoves(), san),
+            };
+
+            Some(AnimationFrame {
+                fen: fen(&move_node.position),
+                last_move: LastMove::from_move(played_move),
+                caption: Some(caption),
+            })
+        }))
+        .collect()
+}
+
+pub fn save_gif(
+    frames: &[AnimationFrame],
+    options: &RenderOptions,
+    settings: AnimationSettings,
+    path: &Path,
+) -> Result<(), RenderError> {
+    let write_error = |cause: String| RenderError::Write {
+        path: path.display().to_string(),
+        cause,
+    };
+    let options = &RenderOptions {
+        size: options.size.min(GIF_MAX_BOARD_SIZE),
+        ..options.clone()
+    };
+    let (width, height) = frame_size(options, settings);
+    let file = File::create(path).map_err(|err| write_error(err.to_string()))?;
+    let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])
+        .map_err(|err| write_error(err.to_string()))?;
+    encoder
+        .set(gif::Repeat::Infinite)
+        .map_err(|err| write_error(err.to_string()))?;
+
+    for frame in frames {
+        let mut pixels = rgba_pixels(render_frame(frame, options, settings)?)?;
+        let mut gif_frame = gif::Frame::from_rgba_speed(
+            width as u16,
+            height as u16,
+            &mut pixels,
+            GIF_QUANTIZATION_SPEED,
+        );
+        // Gif delays are counted in hundredths of a second.
+        gif_frame.delay = (settings.delay_ms / 10).min(u16::MAX as u32) as u16;
+        encoder
+            .write_frame(&gif_frame)
+            .map_err(|err| write_error(err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+fn caption_height(options: &RenderOptions) -> u32 {
+    options.size / 10
+}
+
+fn frame_size(options: &RenderOptions, settings: AnimationSettings) -> (u32, u32) {
+    if settings.captions {
+        (options.size, options.size + caption_height(options))
+    } else {
+        (options.size, options.size)
+    }
+}
+
+fn render_frame(
+    frame: &AnimationFrame,
+    options: &RenderOptions,
+    settings: AnimationSettings,
+) -> Result<ImageSurface, RenderError> {
+    let board_options = RenderOptions {
+        last_move: frame.last_move,
+        annotations: GraphicalAnnotations::default(),
+        ..options.clone()
+    };
+    let board = render_position(&frame.fen, &board_options)?;
+    if !settings.captions {
+        return Ok(board);
+    }
+
+    let (width, height) = frame_size(options, settings);
+    let surface =
+        ImageSurface::create(Format::ARgb32, width as i32, height as i32).map_err(|status| {
+            RenderError::Surface {
+                cause: format!("{:?}", status),
+            }
+        })?;
+    {
+        let context = Context::new(&surface);
+        let (bg_red, bg_green, bg_blue) = options.background_color;
+        context.set_source_rgb(bg_red, bg_green, bg_blue);
+        context.paint();
+        context.set_source_surface(&board, 0.0, 0.0);
+        context.paint();
+
+        if let Some(caption) = &frame.caption {
+            let board_size = options.size as f64;
+            let caption_height = caption_height(options) as f64;
+            let (text_red, text_green, text_blue) = options.coordinates_color;
+            context.set_source_rgb(text_red, text_green, text_blue);
+            context.set_font_size(caption_height * 0.6);
+            let extents = context.text_extents(caption);
+            context.move_to(
+                board_size / 2.0 - extents.width / 2.0 - extents.x_bearing,
+                board_size + caption_height / 2.0 - extents.height / 2.0 - extents.y_bearing,
+            );
+            context.show_text(caption);
+        }
+    }
+
+    Ok(surface)
+}
+
+// Cairo stores each pixel as a native endian 0xAARRGGBB word, with premultiplied alpha.
+fn rgba_pixels(mut surface: ImageSurface) -> Result<Vec<u8>, RenderError> {
+    surface.flush();
+    let width = surface.get_width() as usize;
+    let stride = surface.get_stride() as usize;
+    let data = surface.get_data().map_err(|err| RenderError::Surface {
+        cause: format!("{:?}", err),
+    })?;
+
+    let mut pixels = Vec::with_capacity(width * data.len() / stride * 4);
+    for row in data.chunks(stride) {
+        for pixel in row[..width * 4].chunks(4) {
+            let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let alpha = argb >> 24;
+            let unpremultiply = |channel: u32| {
+                if alpha == 0 {
+                    0
+                } else {
+                    (channel * 255 / alpha) as u8
+                }
+            };
+            pixels.push(unpremultiply((argb >> 16) & 0xff));
+            pixels.push(unpremultiply((argb >> 8) & 0xff));
+            pixels.push(unpremultiply(argb & 0xff));
+            pixels.push(alpha as u8);
+        }
+    }
+
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_game;
+
+    #[test]
+    fn animates_the_line_through_a_variation() {
+        let tree = parse_game("1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 *")
+            .unwrap()
+            .tree;
+        let frames = line_frames(&tree, 3);
+
+        let captions = frames
+            .iter()
+            .map(|frame| frame.caption.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            captions,
+            vec![None, Some("1. e4"), Some("1... c5"), Some("2. Nf3")]
+        );
+        assert_eq!(frames[0].fen, fen(&tree.node(GameTree::ROOT).position));
+        assert_eq!(frames[0].last_move, None);
+        assert_eq!(
+            frames[1].last_move,
+            LastMove::from_move(tree.node(1).move_played.as_ref().unwrap())
+        );
+        assert_eq!(frames[3].fen, fen(&tree.node(4).position));
+    }
+
+    #[test]
+    fn leaves_room_for_the_captions() {
+        let options = RenderOptions {
+            size: 400,
+            ..RenderOptions::default()
+        };
+        let mut settings = AnimationSettings::default();
+
+        assert_eq!(frame_size(&options, settings), (400, 440));
+        settings.captions = false;
+        assert_eq!(frame_size(&options, settings), (400, 400));
+    }
+
+    #[test]
+    fn converts_premultiplied_pixels_to_rgba() {
+        let surface = ImageSurface::create(Format::ARgb32, 3, 1).unwrap();
+        {
+            let context = Context::new(&surface);
+            context.set_source_rgba(1.0, 0.0, 0.0, 1.0);
+            context.rectangle(0.0, 0.0, 1.0, 1.0);
+            context.fill();
+            context.set_source_rgba(0.0, 0.0, 1.0, 0.5);
+            context.rectangle(1.0, 0.0, 1.0, 1.0);
+            context.fill();
+        }
+
+        let pixels = rgba_pixels(surface).unwrap();
+        assert_eq!(pixels.len(), 3 * 4);
+        assert_eq!(pixels[0..4], [255, 0, 0, 255]);
+        // The half transparent blue keeps its full intensity.
+        assert_eq!(pixels[4..6], [0, 0]);
+        assert!(pixels[6] >= 254);
+        assert!((127..=128).contains(&pixels[7]));
+        assert_eq!(pixels[8..12], [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,216 @@
+use failure::Fail;
+
+use cairo::{Context, Format, ImageSurface, SvgSurface};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::rc::Rc;
+
+use super::chess_board_painter::ChessBoardPainter;
+use super::chess_board_widget::{
+    BlackSide, ChessState, ChessStateBuilder, DndState, LastMove, SelectionState,
+};
+use crate::pgn::{position_from_fen, GraphicalAnnotations};
+
+#[derive(Debug, Fail)]
+pub enum RenderError {
+    #[fail(display = "Invalid position: {}", fen)]
+    InvalidPosition { fen: String },
+    #[fail(display = "Could not create the drawing surface: {}", cause)]
+    Surface { cause: String },
+    #[fail(display = "Could not write {}: {}", path, cause)]
+    Write { path: String, cause: String },
+    #[fail(display = "Go to a position of the game to export its line")]
+    NoLine,
+}
+
+thread_local! {
+    // Rasterising the pieces images is the slow part of a render: painters are kept
+    // for each cells size.
+    static PAINTERS: RefCell<HashMap<u32, Rc<ChessBoardPainter>>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub size: u32,
+    pub black_side: BlackSide,
+    pub background_color: (f64, f64, f64),
+    pub white_cells_color: (f64, f64, f64),
+    pub black_cells_color: (f64, f64, f64),
+    pub coordinates_color: (f64, f64, f64),
+    pub last_move_arrow_color: (f64, f64, f64),
+    pub last_move: Option<LastMove>,
+    pub annotations: GraphicalAnnotations,
+}
+
+// Same look as a freshly opened board.
+impl Default for RenderOptions {
+    fn default() -> Self {
+        ChessStateBuilder::new().build().render_options()
+    }
+}
+
+pub fn render_position(fen: &str, options: &RenderOptions) -> Result<ImageSurface, RenderError> {
+    let surface = ImageSurface::create(Format::ARgb32, options.size as i32, options.size as i32)
+        .map_err(|status| RenderError::Surface {
+            cause: format!("{:?}", status),
+        })?;
+    paint_position(&Context::new(&surface), fen, options)?;
+
+    Ok(surface)
+}
+
+pub fn render_thumbnail(fen: &str, size: u32, black_side: BlackSide) -> Option<ImageSurface> {
+    let options = RenderOptions {
+        size,
+        black_side,
+        ..RenderOptions::default()
+    };
+    render_position(fen, &options).ok()
+}
+
+pub fn save_png(fen: &str, options: &RenderOptions, path: &Path) -> Result<(), RenderError> {
+    let surface = render_position(fen, options)?;
+    let mut file = File::create(path).map_err(|err| RenderError::Write {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    })?;
+
+    surface
+        .write_to_png(&mut file)
+        .map_err(|err| RenderError::Write {
+            path: path.display().to_string(),
+            cause: format!("{:?}", err),
+        })
+}
+
+pub fn save_svg(fen: &str, options: &RenderOptions, path: &Path) -> Result<(), RenderError> {
+    let size = options.size as f64;
+    let surface = SvgSurface::new(size, size, Some(path)).map_err(|status| RenderError::Write {
+        path: path.display().to_string(),
+        cause: format!("{:?}", status),
+    })?;
+    paint_position(&Context::new(&surface), fen, options)?;
+    surface.finish();
+
+    Ok(())
+}
+
+/// Svg when the file name says so, png otherwise.
+pub fn save_diagram(fen: &str, options: &RenderOptions, path: &Path) -> Result<(), RenderError> {
+    let is_svg = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        save_svg(fen, options, path)
+    } else {
+        save_png(fen, options, path)
+    }
+}
+
+fn paint_position(
+    context: &Context,
+    fen: &str,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    let chess_state = chess_state_for(fen, options)?;
+    painter_for(options.size / 9).paint(
+        context,
+        &chess_state,
+        &DndState::default(),
+        &SelectionState::default(),
+    );
+
+    Ok(())
+}
+
+fn painter_for(cells_size: u32) -> Rc<ChessBoardPainter> {
+    PAINTERS.with(|painters| {
+        painters
+            .borrow_mut()
+            .entry(cells_size)
+            .or_insert_with(|| {
+                let mut painter = ChessBoardPainter::new(cells_size);
+                painter.build_images();
+                Rc::new(painter)
+            })
+            .clone()
+    })
+}
+
+fn chess_state_for(fen: &str, options: &RenderOptions) -> Result<ChessState, RenderError> {
+    let mut state_builder = ChessStateBuilder::new();
+    state_builder.set_board_size(options.size);
+    state_builder.set_board_orientation(options.black_side);
+    state_builder.set_board_background_color(options.background_color);
+    state_builder.set_board_white_cells_color(options.white_cells_color);
+    state_builder.set_board_black_cells_color(options.black_cells_color);
+    state_builder.set_board_coordinates_color(options.coordinates_color);
+    state_builder.set_last_move_arrow_color(options.last_move_arrow_color);
+
+    let mut chess_state = state_builder.build();
+    chess_state.board = position_from_fen(fen).ok_or_else(|| RenderError::InvalidPosition {
+        fen: fen.to_string(),
+    })?;
+    chess_state.interactive = false;
+    chess_state.last_move = options.last_move;
+    chess_state.user_annotations = options.annotations.clone();
+    chess_state.game_over = chess_state.detect_outcome();
+
+    Ok(chess_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn options(size: u32) -> RenderOptions {
+        RenderOptions {
+            size,
+            ..RenderOptions::default()
+        }
+    }
+
+    #[test]
+    fn renders_at_the_requested_size() {
+        let surface = render_position(START, &options(180)).unwrap();
+
+        assert_eq!(surface.get_width(), 180);
+        assert_eq!(surface.get_height(), 180);
+    }
+
+    #[test]
+    fn refuses_an_invalid_position() {
+        match render_position("not a position", &options(90)) {
+            Err(RenderError::InvalidPosition { fen }) => assert_eq!(fen, "not a position"),
+            other => panic!("unexpected render result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn saves_the_diagram_in_the_format_of_the_file_name() {
+        for extension in &["png", "SVG"] {
+            let path = std::env::temp_dir().join(format!(
+                "chess-pgn-reviser-{}-diagram.{}",
+                std::process::id(),
+                extension
+            ));
+            save_diagram(START, &options(90), &path).unwrap();
+
+            let content = fs::read(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            if *extension == "png" {
+                assert!(content.starts_with(b"\x89PNG"));
+            } else {
+                assert!(String::from_utf8_lossy(&content).contains("<svg"));
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use relm::Channel;
+use shakmaty::uci::Uci;
+
+use std::path::Path;
+
+use crate::engine::{EngineError, EngineEvent, SearchLimit, StrengthLimit, UciEngine};
+
+// The engine is declared first so that it is shut down before its channel is dropped.
+pub struct EngineRunner {
+    engine: UciEngine,
+    _channel: Channel<EngineEvent>,
+    pending_searches: u32,
+}
+
+impl EngineRunner {
+    pub fn start<F>(
+        path: &Path,
+        strength: Option<StrengthLimit>,
+        on_event: F,
+    ) -> Result<EngineRunner, EngineError>
+    where
+        F: FnMut(EngineEvent) + 'static,
+    {
+        let (channel, sender) = Channel::new(on_event);
+        let mut engine = UciEngine::spawn_with_sender(path, sender)?;
+        if let Some(strength) = strength {
+            strength.configure(&mut engine)?;
+        }
+        engine.new_game()?;
+        engine.is_ready()?;
+
+        Ok(EngineRunner {
+            engine,
+            _channel: channel,
+            pending_searches: 0,
+        })
+    }
+
+    pub fn search(
+        &mut self,
+        fen: &str,
+        moves: &[Uci],
+        limit: SearchLimit,
+    ) -> Result<(), EngineError> {
+        if self.pending_searches > 0 {
+            self.engine.stop()?;
+        }
+        self.engine.set_position(fen, moves)?;
+        self.engine.go(limit)?;
+        self.pending_searches += 1;
+
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> Result<(), EngineError> {
+        if self.pending_searches > 0 {
+            self.engine.stop()?;
+        }
+        self.engine.new_game()
+    }
+
+    /// Infos are only relevant while the latest search is the single one running.
+    pub fn is_latest_search_running(&self) -> bool {
+        self.pending_searches == 1
+    }
+
+    /// To be called on each bestmove: tells whether it answers the latest search.
+    pub fn search_finished(&mut self) -> bool {
+        self.pending_searches = self.pending_searches.saturating_sub(1);
+        self.pending_searches == 0
+    }
+}
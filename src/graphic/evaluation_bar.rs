@@ -0,0 +1,191 @@
+use gtk::prelude::*;
+use gtk::Inhibit;
+use relm::{Relm, Widget};
+use relm_derive::{widget, Msg};
+use shakmaty::Color;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::chess_board::BlackSide;
+use crate::engine::Score;
+
+const BAR_WIDTH: i32 = 28;
+const WHITE_SIDE_COLOR: (f64, f64, f64) = (0.95, 0.95, 0.92);
+const BLACK_SIDE_COLOR: (f64, f64, f64) = (0.20, 0.20, 0.22);
+const MIDDLE_LINE_COLOR: (f64, f64, f64) = (0.85, 0.20, 0.20);
+const TEXT_COLOR: (f64, f64, f64) = (0.50, 0.50, 0.50);
+
+struct EvaluationState {
+    // Score always from White's point of view, with the side to move.
+    evaluation: Option<(Score, Color)>,
+    black_side: BlackSide,
+}
+
+pub struct EvaluationBarModel {
+    state: Rc<RefCell<EvaluationState>>,
+    height: i32,
+}
+
+#[derive(Msg)]
+pub enum EvaluationBarMsg {
+    SetScore(Option<(Score, Color)>),
+    SetBlackSide(BlackSide),
+}
+
+#[widget]
+impl Widget for EvaluationBar {
+    fn model(_: &Relm<Self>, height: i32) -> EvaluationBarModel {
+        EvaluationBarModel {
+            state: Rc::new(RefCell::new(EvaluationState {
+                evaluation: None,
+                black_side: BlackSide::BlackTop,
+            })),
+            height,
+        }
+    }
+
+    fn update(&mut self, event: EvaluationBarMsg) {
+        match event {
+            EvaluationBarMsg::SetScore(evaluation) => {
+                self.model.state.borrow_mut().evaluation = evaluation;
+            }
+            EvaluationBarMsg::SetBlackSide(black_side) => {
+                self.model.state.borrow_mut().black_side = black_side;
+            }
+        }
+        self.canvas.queue_draw();
+    }
+
+    fn init_view(&mut self) {
+        self.canvas.set_size_request(BAR_WIDTH, self.model.height);
+
+        let weak_state = Rc::downgrade(&self.model.state);
+        self.canvas.connect_draw(move |canvas, context| {
+            if let Some(state) = weak_state.upgrade() {
+                let width = canvas.get_allocated_width() as f64;
+                let height = canvas.get_allocated_height() as f64;
+                paint(context, &state.borrow(), width, height);
+            }
+            Inhibit(false)
+        });
+    }
+
+    view! {
+        #[name="canvas"]
+        gtk::DrawingArea {
+        }
+    }
+}
+
+fn paint(context: &cairo::Context, state: &EvaluationState, width: f64, height: f64) {
+    let white_height = height
+        * state.evaluation.map_or(0.5, |(score, side_to_move)| {
+            white_share(score, side_to_move)
+        });
+    let white_at_bottom = state.black_side == BlackSide::BlackTop;
+
+    context.set_source_rgb(BLACK_SIDE_COLOR.0, BLACK_SIDE_COLOR.1, BLACK_SIDE_COLOR.2);
+    context.rectangle(0.0, 0.0, width, height);
+    context.fill();
+
+    let white_top = if white_at_bottom {
+        height - white_height
+    } else {
+        0.0
+    };
+    context.set_source_rgb(WHITE_SIDE_COLOR.0, WHITE_SIDE_COLOR.1, WHITE_SIDE_COLOR.2);
+    context.rectangle(0.0, white_top, width, white_height);
+    context.fill();
+
+    context.set_source_rgb(
+        MIDDLE_LINE_COLOR.0,
+        MIDDLE_LINE_COLOR.1,
+        MIDDLE_LINE_COLOR.2,
+    );
+    context.set_line_width(1.0);
+    context.move_to(0.0, height / 2.0);
+    context.line_to(width, height / 2.0);
+    context.stroke();
+
+    if let Some((score, side_to_move)) = state.evaluation {
+        draw_score_text(context, score, side_to_move, width, height, white_at_bottom);
+    }
+}
+
+fn draw_score_text(
+    context: &cairo::Context,
+    score: Score,
+    side_to_move: Color,
+    width: f64,
+    height: f64,
+    white_at_bottom: bool,
+) {
+    let text = score_text(score);
+    let white_is_better = white_share(score, side_to_move) >= 0.5;
+    let text_at_bottom = white_is_better == white_at_bottom;
+
+    context.set_source_rgb(TEXT_COLOR.0, TEXT_COLOR.1, TEXT_COLOR.2);
+    context.set_font_size(width * 0.32);
+    let extents = context.text_extents(&text);
+    let x = (width - extents.width) / 2.0 - extents.x_bearing;
+    let y = if text_at_bottom {
+        height - 4.0
+    } else {
+        4.0 + extents.height
+    };
+
+    context.move_to(x, y);
+    context.show_text(&text);
+}
+
+// A mate in 0 has no sign: the side to move is the one mated.
+fn white_share(score: Score, side_to_move: Color) -> f64 {
+    match score {
+        Score::Centipawns(centipawns) => 1.0 / (1.0 + (-0.004 * centipawns as f64).exp()),
+        Score::Mate(moves) if moves > 0 => 1.0,
+        Score::Mate(moves) if moves < 0 => 0.0,
+        Score::Mate(_) => match side_to_move {
+            Color::White => 0.0,
+            Color::Black => 1.0,
+        },
+    }
+}
+
+pub fn score_text(score: Score) -> String {
+    match score {
+        Score::Centipawns(centipawns) => format!("{:+.1}", centipawns as f64 / 100.0),
+        Score::Mate(moves) => format!("M{}", moves.abs()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_even_position_shares_the_bar() {
+        assert!((white_share(Score::Centipawns(0), Color::White) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn centipawns_move_the_bar_symmetrically() {
+        let white_better = white_share(Score::Centipawns(150), Color::Black);
+        let black_better = white_share(Score::Centipawns(-150), Color::White);
+
+        assert!(white_better > 0.5 && white_better < 1.0);
+        assert!((white_better + black_better - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_mate_fills_the_bar_for_the_winner() {
+        assert!((white_share(Score::Mate(3), Color::White) - 1.0).abs() < 1e-9);
+        assert!(white_share(Score::Mate(-2), Color::White).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_mate_in_zero_goes_against_the_side_to_move() {
+        assert!(white_share(Score::Mate(0), Color::White).abs() < 1e-9);
+        assert!((white_share(Score::Mate(0), Color::Black) - 1.0).abs() < 1e-9);
+    }
+}
@@ -1,25 +1,147 @@
+use gdk::enums::key;
+use glib::StaticType;
 use gtk::prelude::*;
 use gtk::Inhibit;
-use relm::Widget;
+use relm::{connect, Relm, Widget};
 use relm_derive::{widget, Msg};
+use shakmaty::fen::fen;
+use shakmaty::{Chess, Color, Outcome, Position};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 use super::chess_board::*;
+use super::engine_runner::EngineRunner;
+use super::evaluation_bar::*;
+use super::move_list::*;
+use super::statistics_view::*;
+use crate::engine::{EngineEvent, EngineSettings, MistakeAnalysis, SearchLimit, StrengthLimit};
+use crate::pgn::{
+    nag_description, parse_games, position_from_fen, position_key, write_games, GameTree,
+    GraphicalAnnotations, NodeId, PgnGame,
+};
+use crate::progress::{
+    game_history_text, position_history_text, Attempt, AttemptsDatabase, PositionStatistics,
+    Statistics,
+};
+use crate::reviser::{PlayerKind, Verdict};
+use crate::scheduler::{now_timestamp, Schedule};
+
+const GAMES_HEADERS: [&str; 6] = ["White", "Black", "Event", "Date", "ECO", "Result"];
+const GAME_INDEX_COLUMN: u32 = 6;
+const PLAYER_KINDS: [(PlayerKind, &str); 2] = [
+    (PlayerKind::Human, "Human"),
+    (PlayerKind::Computer, "Computer"),
+];
+const STRENGTH_LIMITS: [&str; 3] = ["Elo", "Depth", "Move time (ms)"];
+const ANALYSIS_LIMIT: SearchLimit = SearchLimit::Depth(20);
+const EXPLANATION_LIMIT: SearchLimit = SearchLimit::MoveTime(1500);
+const STATISTICS_PAGE: u32 = 1;
+const WORST_POSITIONS_COUNT: usize = 10;
 
 pub struct WinModel {
+    relm: Relm<Win>,
     black_side: BlackSide,
+    games: Vec<PgnGame>,
+    games_store: gtk::ListStore,
+    games_filter: gtk::TreeModelFilter,
+    games_filter_text: Rc<RefCell<String>>,
+    selected_game: Option<usize>,
+    session_settings: SessionSettings,
+    animation_settings: AnimationSettings,
+    engine_settings: EngineSettings,
+    engine: Option<EngineRunner>,
+    engine_request: Option<String>,
+    analysis_engine: Option<EngineRunner>,
+    analysis_position: Option<Chess>,
+    show_evaluation: bool,
+    board_fen: Option<String>,
+    explanation_engine: Option<EngineRunner>,
+    mistake_analysis: Option<MistakeAnalysis>,
+    schedule: Schedule,
+    schedule_path: Option<PathBuf>,
+    attempts: AttemptsDatabase,
+    attempts_path: Option<PathBuf>,
+    pgn_path: Option<PathBuf>,
+    user_annotations: HashMap<(usize, NodeId), GraphicalAnnotations>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum HistoryNavigation {
+    First,
+    Back,
+    Forward,
+    Last,
 }
 
 #[derive(Msg)]
 pub enum WinMsg {
     Quit,
     SetBoardUpsideDown,
+    OpenPgnFile,
+    GamesFilterChanged(String),
+    GameSelectionChanged,
+    ConfigureSides,
+    NavigateHistory(HistoryNavigation),
+    BoardMovePlayed(String),
+    BoardGameOver(Outcome),
+    BoardNodeChanged(Option<NodeId>),
+    BoardPositionChanged(String),
+    BoardEngineMoveRequested(String),
+    EngineEvent(EngineEvent),
+    AnalysisEvent(EngineEvent),
+    BoardMoveGuessed(Guess),
+    ExplanationEvent(EngineEvent),
+    ShowEvaluation(bool),
+    MoveListNodeClicked(NodeId),
+    SidePageSwitched(u32),
+    StatisticsDrillRequested(PositionStatistics),
+    BoardUserAnnotationsChanged(Option<NodeId>, GraphicalAnnotations),
+    SavePgnFile,
+    ExportDiagram,
+    ExportLineAnimation,
+    BoardExportFailed(String),
+    BoardAnimationExportStarted,
+    BoardAnimationExportFinished,
 }
 
 #[widget]
 impl Widget for Win {
-    fn model() -> WinModel {
+    fn model(relm: &Relm<Self>, _: ()) -> WinModel {
+        let mut columns_types = vec![String::static_type(); GAMES_HEADERS.len()];
+        columns_types.push(u32::static_type());
+        let games_store = gtk::ListStore::new(&columns_types);
+        let games_filter = gtk::TreeModelFilter::new(&games_store, None);
+        let games_filter_text = Rc::new(RefCell::new(String::new()));
+
         WinModel {
+            relm: relm.clone(),
             black_side: BlackSide::BlackTop,
+            games: vec![],
+            games_store,
+            games_filter,
+            games_filter_text,
+            selected_game: None,
+            session_settings: SessionSettings::default(),
+            animation_settings: AnimationSettings::default(),
+            engine_settings: EngineSettings::default(),
+            engine: None,
+            engine_request: None,
+            analysis_engine: None,
+            analysis_position: None,
+            show_evaluation: false,
+            board_fen: None,
+            explanation_engine: None,
+            mistake_analysis: None,
+            schedule: Schedule::default(),
+            schedule_path: Schedule::default_path(),
+            attempts: AttemptsDatabase::default(),
+            attempts_path: AttemptsDatabase::default_path(),
+            pgn_path: None,
+            user_annotations: HashMap::new(),
         }
     }
 
@@ -31,24 +153,289 @@ impl Widget for Win {
                     BlackSide::BlackBottom => BlackSide::BlackTop,
                     BlackSide::BlackTop => BlackSide::BlackBottom,
                 };
-                self.model.black_side = new_black_side;
-                self.chess_board
-                    .emit(ChessBoardMsg::SetBlackSide(new_black_side));
+                self.set_black_side(new_black_side);
+            }
+            WinMsg::OpenPgnFile => {
+                if let Some(path) = self.choose_pgn_file() {
+                    self.load_pgn_file(path);
+                }
+            }
+            WinMsg::GamesFilterChanged(text) => {
+                *self.model.games_filter_text.borrow_mut() = text.to_lowercase();
+                self.model.games_filter.refilter();
+            }
+            WinMsg::GameSelectionChanged => {
+                if let Some(game_index) = self.selected_game_index() {
+                    self.model.selected_game = Some(game_index);
+                    self.start_session();
+                }
+            }
+            WinMsg::ConfigureSides => {
+                if let Some((settings, engine_settings)) = self.ask_session_settings() {
+                    if engine_settings != self.model.engine_settings {
+                        self.model.engine = None;
+                        self.model.analysis_engine = None;
+                        self.model.explanation_engine = None;
+                    }
+                    let previous_settings = self.model.session_settings;
+                    self.model.session_settings = settings;
+                    self.model.engine_settings = engine_settings;
+                    self.update_orientation_from_sides(previous_settings);
+                    self.start_session();
+                }
+            }
+            WinMsg::NavigateHistory(navigation) => {
+                self.navigate_history(navigation);
+            }
+            WinMsg::BoardMovePlayed(san) => {
+                self.status_label
+                    .set_text(&format!("Last move played: {}", san));
+            }
+            WinMsg::BoardGameOver(outcome) => {
+                let result = match outcome {
+                    Outcome::Decisive {
+                        winner: Color::White,
+                    } => "White wins",
+                    Outcome::Decisive {
+                        winner: Color::Black,
+                    } => "Black wins",
+                    Outcome::Draw => "Draw",
+                };
+                self.status_label
+                    .set_text(&format!("Game over: {}", result));
+            }
+            WinMsg::BoardNodeChanged(node) => {
+                self.move_list.emit(MoveListMsg::SetCurrentNode(node));
+                self.update_annotations_pane(node);
+                self.restore_user_annotations(node);
+            }
+            WinMsg::BoardEngineMoveRequested(position_fen) => {
+                self.request_engine_move(position_fen);
+            }
+            WinMsg::EngineEvent(event) => {
+                self.handle_engine_event(event);
+            }
+            WinMsg::BoardPositionChanged(position_fen) => {
+                self.model.board_fen = Some(position_fen.clone());
+                self.model.mistake_analysis = None;
+                self.explanation_label.set_text("");
+                self.update_history_pane();
+                self.analyse_position(position_fen);
+            }
+            WinMsg::AnalysisEvent(event) => {
+                self.handle_analysis_event(event);
+            }
+            WinMsg::BoardMoveGuessed(guess) => {
+                self.record_guess(&guess);
+                if guess.verdict == Verdict::Wrong {
+                    self.explain_mistake(guess);
+                }
+            }
+            WinMsg::ExplanationEvent(event) => {
+                self.handle_explanation_event(event);
+            }
+            WinMsg::ShowEvaluation(show_evaluation) => {
+                self.model.show_evaluation = show_evaluation;
+                if let (true, Some(position_fen)) = (show_evaluation, self.model.board_fen.clone())
+                {
+                    self.analyse_position(position_fen);
+                } else if !show_evaluation {
+                    self.model.analysis_engine = None;
+                    self.model.analysis_position = None;
+                    self.evaluation_bar.emit(EvaluationBarMsg::SetScore(None));
+                }
+            }
+            WinMsg::MoveListNodeClicked(node) => {
+                self.chess_board.emit(ChessBoardMsg::GoToNode(node));
+            }
+            WinMsg::SidePageSwitched(page) => {
+                if page == STATISTICS_PAGE {
+                    self.update_statistics();
+                }
+            }
+            WinMsg::StatisticsDrillRequested(position) => {
+                self.drill_position(position);
+            }
+            WinMsg::BoardUserAnnotationsChanged(node, annotations) => {
+                if let (Some(game_index), Some(node)) = (self.model.selected_game, node) {
+                    self.model
+                        .user_annotations
+                        .insert((game_index, node), annotations);
+                } else {
+                    self.status_label
+                        .set_text("Annotations drawn outside of the game are not saved");
+                }
+            }
+            WinMsg::SavePgnFile => {
+                if let Some(path) = self.choose_pgn_save_file() {
+                    self.save_pgn_file(path);
+                }
+            }
+            WinMsg::ExportDiagram => {
+                if let Some(path) = self.choose_diagram_file() {
+                    self.chess_board.emit(ChessBoardMsg::ExportDiagram(path));
+                }
+            }
+            WinMsg::ExportLineAnimation => {
+                if let Some((path, settings)) = self.choose_animation_file() {
+                    self.model.animation_settings = settings;
+                    self.chess_board
+                        .emit(ChessBoardMsg::ExportLineAnimation(path, settings));
+                }
+            }
+            WinMsg::BoardExportFailed(message) => {
+                self.show_error(&message);
+            }
+            WinMsg::BoardAnimationExportStarted => {
+                self.status_label
+                    .set_text("Exporting the line animation...");
+            }
+            WinMsg::BoardAnimationExportFinished => {
+                self.status_label.set_text("");
             }
         }
     }
 
+    fn init_view(&mut self) {
+        self.setup_games_view();
+        self.setup_side_notebook();
+        self.setup_history_keys();
+        self.connect_board_outputs();
+        self.load_progress();
+    }
+
     view! {
+        #[name="window"]
         gtk::Window {
             title: "Chess pgn reviser",
-            gtk::Box(gtk::Orientation::Vertical, 5) {
-                #[name="chess_board"]
-                ChessBoard(500) {
-
+            gtk::Box(gtk::Orientation::Horizontal, 5) {
+                gtk::Box(gtk::Orientation::Vertical, 5) {
+                    gtk::Box(gtk::Orientation::Horizontal, 5) {
+                        #[name="evaluation_bar"]
+                        EvaluationBar(500) {
+                        },
+                        #[name="chess_board"]
+                        ChessBoard(500) {
+                        },
+                    },
+                    gtk::Box(gtk::Orientation::Horizontal, 2) {
+                        gtk::Button {
+                            label: "|<",
+                            tooltip_text: Some("First position"),
+                            clicked() => WinMsg::NavigateHistory(HistoryNavigation::First),
+                        },
+                        gtk::Button {
+                            label: "<",
+                            tooltip_text: Some("Previous position"),
+                            clicked() => WinMsg::NavigateHistory(HistoryNavigation::Back),
+                        },
+                        gtk::Button {
+                            label: ">",
+                            tooltip_text: Some("Next position"),
+                            clicked() => WinMsg::NavigateHistory(HistoryNavigation::Forward),
+                        },
+                        gtk::Button {
+                            label: ">|",
+                            tooltip_text: Some("Last position"),
+                            clicked() => WinMsg::NavigateHistory(HistoryNavigation::Last),
+                        },
+                    },
+                    gtk::Button {
+                        label: "Toggle board orientation",
+                        clicked() => Some(WinMsg::SetBoardUpsideDown),
+                    },
+                    gtk::Button {
+                        label: "Configure sides",
+                        clicked() => Some(WinMsg::ConfigureSides),
+                    },
+                    gtk::Button {
+                        label: "Export position as diagram",
+                        clicked() => Some(WinMsg::ExportDiagram),
+                    },
+                    gtk::Button {
+                        label: "Export line as animated gif",
+                        clicked() => Some(WinMsg::ExportLineAnimation),
+                    },
+                    gtk::CheckButton {
+                        label: "Show engine evaluation",
+                        toggled(button) => WinMsg::ShowEvaluation(button.get_active()),
+                    },
+                    #[name="status_label"]
+                    gtk::Label {
+                        text: "",
+                    },
+                },
+                gtk::Box(gtk::Orientation::Vertical, 5) {
+                    #[name="move_list"]
+                    MoveList {
+                    },
+                    gtk::ScrolledWindow {
+                        min_content_height: 150,
+                        #[name="annotations_view"]
+                        gtk::TextView {
+                            editable: false,
+                            cursor_visible: false,
+                            wrap_mode: gtk::WrapMode::Word,
+                            left_margin: 5,
+                            right_margin: 5,
+                        },
+                    },
+                    gtk::Frame {
+                        label: Some("Revision history"),
+                        gtk::ScrolledWindow {
+                            min_content_height: 150,
+                            #[name="history_view"]
+                            gtk::TextView {
+                                editable: false,
+                                cursor_visible: false,
+                                wrap_mode: gtk::WrapMode::Word,
+                                left_margin: 5,
+                                right_margin: 5,
+                            },
+                        },
+                    },
+                    #[name="explanation_label"]
+                    gtk::Label {
+                        text: "",
+                        line_wrap: true,
+                        max_width_chars: 40,
+                        selectable: true,
+                        xalign: 0.0,
+                    },
                 },
-                gtk::Button {
-                    label: "Toggle board orientation",
-                    clicked() => Some(WinMsg::SetBoardUpsideDown),
+                #[name="side_notebook"]
+                gtk::Notebook {
+                    switch_page(_, _, page) => WinMsg::SidePageSwitched(page),
+                    #[name="games_page"]
+                    gtk::Box(gtk::Orientation::Vertical, 5) {
+                        gtk::Button {
+                            label: "Open pgn file",
+                            clicked() => Some(WinMsg::OpenPgnFile),
+                        },
+                        gtk::Button {
+                            label: "Save pgn file with my annotations",
+                            clicked() => Some(WinMsg::SavePgnFile),
+                        },
+                        gtk::SearchEntry {
+                            placeholder_text: Some("Filter games"),
+                            changed(entry) => WinMsg::GamesFilterChanged(
+                                entry.get_text().map(|text| text.to_string()).unwrap_or_default()
+                            ),
+                        },
+                        gtk::ScrolledWindow {
+                            min_content_width: 450,
+                            child: {
+                                expand: true,
+                                fill: true,
+                            },
+                            #[name="games_view"]
+                            gtk::TreeView {
+                            },
+                        },
+                    },
+                    #[name="statistics_view"]
+                    StatisticsView {
+                    },
                 },
             },
             delete_event(_self, _event) => (WinMsg::Quit, Inhibit(false)),
@@ -56,6 +443,1035 @@ impl Widget for Win {
     }
 }
 
+impl Win {
+    fn setup_games_view(&self) {
+        let filter_text = self.model.games_filter_text.clone();
+        self.model
+            .games_filter
+            .set_visible_func(move |model, iter| {
+                let filter_text = filter_text.borrow();
+                filter_text.is_empty()
+                    || (0..GAMES_HEADERS.len()).any(|column| {
+                        model
+                            .get_value(iter, column as i32)
+                            .get::<String>()
+                            .ok()
+                            .flatten()
+                            .map_or(false, |value| value.to_lowercase().contains(&*filter_text))
+                    })
+            });
+
+        let sorted_games = gtk::TreeModelSort::new(&self.model.games_filter);
+        self.games_view.set_model(Some(&sorted_games));
+
+        for (column_index, title) in GAMES_HEADERS.iter().enumerate() {
+            let cell = gtk::CellRendererText::new();
+            let column = gtk::TreeViewColumn::new();
+            column.set_title(title);
+            column.set_resizable(true);
+            column.pack_start(&cell, true);
+            column.add_attribute(&cell, "text", column_index as i32);
+            column.set_sort_column_id(column_index as i32);
+            self.games_view.append_column(&column);
+        }
+
+        let selection = self.games_view.get_selection();
+        connect!(
+            self.model.relm,
+            selection,
+            connect_changed(_),
+            WinMsg::GameSelectionChanged
+        );
+    }
+
+    fn setup_side_notebook(&self) {
+        self.side_notebook
+            .set_tab_label_text(&self.games_page, "Games");
+        self.side_notebook
+            .set_tab_label_text(self.statistics_view.widget(), "Statistics");
+    }
+
+    fn update_annotations_pane(&self, node: Option<NodeId>) {
+        let game = self
+            .model
+            .selected_game
+            .and_then(|game_index| self.model.games.get(game_index));
+        let text = match game {
+            Some(game) => annotations_text(game, node),
+            None => String::new(),
+        };
+
+        if let Some(buffer) = self.annotations_view.get_buffer() {
+            buffer.set_text(&text);
+        }
+    }
+
+    // The board forgets its drawings when leaving a node: bring back the unsaved ones.
+    fn restore_user_annotations(&self, node: Option<NodeId>) {
+        let stored = match (self.model.selected_game, node) {
+            (Some(game_index), Some(node)) => self.model.user_annotations.get(&(game_index, node)),
+            _ => None,
+        };
+        if let Some(annotations) = stored {
+            self.chess_board
+                .emit(ChessBoardMsg::SetUserAnnotations(annotations.clone()));
+        }
+    }
+
+    // Arrow keys belong to the text widgets while they have the focus.
+    fn setup_history_keys(&self) {
+        let stream = self.model.relm.stream().clone();
+        self.window.connect_key_press_event(move |window, event| {
+            // Read only texts, such as the annotations or the revision history, leave the keys
+            // to the game navigation.
+            let is_typing = window.get_focus().map_or(false, |widget| {
+                if let Some(entry) = widget.downcast_ref::<gtk::Entry>() {
+                    entry.get_editable()
+                } else if let Some(text_view) = widget.downcast_ref::<gtk::TextView>() {
+                    text_view.get_editable()
+                } else {
+                    false
+                }
+            });
+            let navigation = match event.get_keyval() {
+                key::Left => Some(HistoryNavigation::Back),
+                key::Right => Some(HistoryNavigation::Forward),
+                key::Home => Some(HistoryNavigation::First),
+                key::End => Some(HistoryNavigation::Last),
+                _ => None,
+            };
+
+            match navigation {
+                Some(navigation) if !is_typing => {
+                    stream.emit(WinMsg::NavigateHistory(navigation));
+                    Inhibit(true)
+                }
+                _ => Inhibit(false),
+            }
+        });
+    }
+
+    fn navigate_history(&self, navigation: HistoryNavigation) {
+        let message = match navigation {
+            HistoryNavigation::First => ChessBoardMsg::HistoryFirst,
+            HistoryNavigation::Back => ChessBoardMsg::HistoryBack,
+            HistoryNavigation::Forward => ChessBoardMsg::HistoryForward,
+            HistoryNavigation::Last => ChessBoardMsg::HistoryLast,
+        };
+        self.chess_board.emit(message);
+    }
+
+    fn connect_board_outputs(&self) {
+        let chess_board = &self.chess_board;
+        connect!(
+            chess_board@ChessBoardMsg::MovePlayed { ref san, .. },
+            self.model.relm,
+            WinMsg::BoardMovePlayed(san.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::GameOver(outcome),
+            self.model.relm,
+            WinMsg::BoardGameOver(outcome)
+        );
+        connect!(
+            chess_board@ChessBoardMsg::CurrentNodeChanged(node),
+            self.model.relm,
+            WinMsg::BoardNodeChanged(node)
+        );
+        connect!(
+            chess_board@ChessBoardMsg::PositionChanged(ref position_fen),
+            self.model.relm,
+            WinMsg::BoardPositionChanged(position_fen.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::EngineMoveRequested(ref position_fen),
+            self.model.relm,
+            WinMsg::BoardEngineMoveRequested(position_fen.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::MoveGuessed(ref guess),
+            self.model.relm,
+            WinMsg::BoardMoveGuessed(guess.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::UserAnnotationsChanged(node, ref annotations),
+            self.model.relm,
+            WinMsg::BoardUserAnnotationsChanged(node, annotations.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::ExportFailed(ref message),
+            self.model.relm,
+            WinMsg::BoardExportFailed(message.clone())
+        );
+        connect!(
+            chess_board@ChessBoardMsg::LineAnimationExportStarted,
+            self.model.relm,
+            WinMsg::BoardAnimationExportStarted
+        );
+        connect!(
+            chess_board@ChessBoardMsg::LineAnimationExportFinished,
+            self.model.relm,
+            WinMsg::BoardAnimationExportFinished
+        );
+
+        let move_list = &self.move_list;
+        connect!(
+            move_list@MoveListMsg::NodeClicked(node),
+            self.model.relm,
+            WinMsg::MoveListNodeClicked(node)
+        );
+
+        let statistics_view = &self.statistics_view;
+        connect!(
+            statistics_view@StatisticsViewMsg::DrillRequested(ref position),
+            self.model.relm,
+            WinMsg::StatisticsDrillRequested(position.clone())
+        );
+    }
+
+    fn start_engine<F>(
+        &self,
+        strength: Option<StrengthLimit>,
+        to_message: F,
+    ) -> Option<EngineRunner>
+    where
+        F: Fn(EngineEvent) -> WinMsg + 'static,
+    {
+        let path = match &self.model.engine_settings.path {
+            Some(path) => path.clone(),
+            None => {
+                self.status_label
+                    .set_text("No engine configured, see Configure sides");
+                return None;
+            }
+        };
+
+        let stream = self.model.relm.stream().clone();
+        match EngineRunner::start(&path, strength, move |event| stream.emit(to_message(event))) {
+            Ok(engine) => Some(engine),
+            Err(error) => {
+                self.show_error(&error.to_string());
+                None
+            }
+        }
+    }
+
+    fn request_engine_move(&mut self, position_fen: String) {
+        if self.model.engine.is_none() {
+            let strength = self.model.engine_settings.strength;
+            self.model.engine = self.start_engine(Some(strength), WinMsg::EngineEvent);
+        }
+
+        let limit = self.model.engine_settings.strength.search_limit();
+        let sent = self
+            .model
+            .engine
+            .as_mut()
+            .map(|engine| engine.search(&position_fen, &[], limit));
+
+        match sent {
+            Some(Ok(())) => {
+                self.model.engine_request = Some(position_fen);
+                self.status_label.set_text("Engine is thinking...");
+            }
+            Some(Err(error)) => {
+                self.model.engine = None;
+                self.show_error(&error.to_string());
+            }
+            None => {}
+        }
+    }
+
+    fn handle_engine_event(&mut self, event: EngineEvent) {
+        match event {
+            EngineEvent::BestMove(best_move) => {
+                let latest_search = self
+                    .model
+                    .engine
+                    .as_mut()
+                    .map_or(false, |engine| engine.search_finished());
+                if !latest_search {
+                    return;
+                }
+                if let (Some(position_fen), Some(uci)) =
+                    (self.model.engine_request.take(), best_move.best_move)
+                {
+                    self.chess_board.emit(ChessBoardMsg::PlayEngineMove {
+                        fen: position_fen,
+                        uci: uci.to_string(),
+                    });
+                }
+            }
+            EngineEvent::Terminated => {
+                self.model.engine = None;
+                self.model.engine_request = None;
+                self.status_label.set_text("The engine has stopped");
+            }
+            _ => {}
+        }
+    }
+
+    fn analyse_position(&mut self, position_fen: String) {
+        if !self.model.show_evaluation {
+            return;
+        }
+        self.evaluation_bar.emit(EvaluationBarMsg::SetScore(None));
+        let position = match position_from_fen(&position_fen) {
+            Some(position) if !position.is_game_over() => position,
+            _ => {
+                self.model.analysis_position = None;
+                return;
+            }
+        };
+
+        if self.model.analysis_engine.is_none() {
+            self.model.analysis_engine = self.start_engine(None, WinMsg::AnalysisEvent);
+        }
+        let sent = self
+            .model
+            .analysis_engine
+            .as_mut()
+            .map(|engine| engine.search(&position_fen, &[], ANALYSIS_LIMIT));
+
+        match sent {
+            Some(Ok(())) => self.model.analysis_position = Some(position),
+            Some(Err(error)) => {
+                self.model.analysis_engine = None;
+                self.show_error(&error.to_string());
+            }
+            None => {}
+        }
+    }
+
+    fn handle_analysis_event(&mut self, event: EngineEvent) {
+        match event {
+            EngineEvent::Info(info) => {
+                let is_latest_search = self
+                    .model
+                    .analysis_engine
+                    .as_ref()
+                    .map_or(false, |engine| engine.is_latest_search_running());
+                let side_to_move = self
+                    .model
+                    .analysis_position
+                    .as_ref()
+                    .map(|position| position.turn());
+                let is_main_line = info.multipv.map_or(true, |multipv| multipv == 1);
+                if let (true, true, Some(side_to_move), Some((score, _))) =
+                    (is_latest_search, is_main_line, side_to_move, info.score)
+                {
+                    self.evaluation_bar.emit(EvaluationBarMsg::SetScore(Some((
+                        score.to_white_point_of_view(side_to_move),
+                        side_to_move,
+                    ))));
+                }
+            }
+            EngineEvent::BestMove(_) => {
+                if let Some(engine) = self.model.analysis_engine.as_mut() {
+                    engine.search_finished();
+                }
+            }
+            EngineEvent::Terminated => {
+                self.model.analysis_engine = None;
+                self.model.analysis_position = None;
+            }
+            _ => {}
+        }
+    }
+
+    // A file that could not be understood is never overwritten: its path is forgotten.
+    fn load_progress(&mut self) {
+        if let Some(path) = self.model.schedule_path.clone() {
+            match Schedule::load(&path) {
+                Ok(schedule) => self.model.schedule = schedule,
+                Err(error) => {
+                    self.model.schedule_path = None;
+                    self.show_error(&error.to_string());
+                }
+            }
+        }
+        if let Some(path) = self.model.attempts_path.clone() {
+            match AttemptsDatabase::load(&path) {
+                Ok(attempts) => self.model.attempts = attempts,
+                Err(error) => {
+                    self.model.attempts_path = None;
+                    self.show_error(&error.to_string());
+                }
+            }
+        }
+    }
+
+    fn record_guess(&mut self, guess: &Guess) {
+        let now = now_timestamp();
+        if let Some(game_index) = self.model.selected_game {
+            if let Some(game) = self.model.games.get(game_index) {
+                self.model
+                    .schedule
+                    .record_guess(&game.tree, guess.node, guess.verdict, now);
+            }
+        }
+
+        let mut errors = vec![];
+        if let Some(path) = &self.model.schedule_path {
+            if let Err(error) = self.model.schedule.save(path) {
+                errors.push(error.to_string());
+            }
+        }
+        if let Some(attempt) = self.attempt_from_guess(guess, now) {
+            let path = self.model.attempts_path.as_ref().map(PathBuf::as_path);
+            if let Err(error) = self.model.attempts.add(attempt, path) {
+                errors.push(error.to_string());
+            }
+        }
+        if !errors.is_empty() {
+            self.show_error(&errors.join("\n"));
+        }
+
+        self.update_history_pane();
+        if self.side_notebook.get_current_page() == Some(STATISTICS_PAGE) {
+            self.update_statistics();
+        }
+    }
+
+    fn attempt_from_guess(&self, guess: &Guess, now: u64) -> Option<Attempt> {
+        let game_index = self.model.selected_game?;
+        let game = self.model.games.get(game_index)?;
+        let file = self.model.pgn_path.as_ref()?;
+
+        Some(Attempt {
+            file: file.display().to_string(),
+            game: game_index,
+            game_title: game_title(game),
+            eco: game.header("ECO").map(String::from),
+            node: guess.node,
+            fen: guess.fen.clone(),
+            timestamp: now,
+            guessed_move: guess.guessed_uci.clone(),
+            expected_move: guess.expected_uci.clone(),
+            verdict: guess.verdict,
+            time_taken_ms: guess.time_taken_ms,
+        })
+    }
+
+    fn update_history_pane(&self) {
+        let now = now_timestamp();
+        let mut sections = vec![];
+        if let (Some(file), Some(game_index)) = (&self.model.pgn_path, self.model.selected_game) {
+            sections.push(game_history_text(
+                &self.model.attempts,
+                &file.display().to_string(),
+                game_index,
+                now,
+            ));
+        }
+        if let Some(position_fen) = &self.model.board_fen {
+            sections.push(position_history_text(
+                &self.model.attempts,
+                position_fen,
+                now,
+            ));
+        }
+
+        if let Some(buffer) = self.history_view.get_buffer() {
+            buffer.set_text(&sections.join("\n\n"));
+        }
+    }
+
+    fn update_statistics(&self) {
+        self.statistics_view
+            .emit(StatisticsViewMsg::SetStatistics(Statistics::compute(
+                &self.model.attempts,
+                WORST_POSITIONS_COUNT,
+            )));
+    }
+
+    // Reopens the game where the position was last revised and starts the session from it.
+    fn drill_position(&mut self, position: PositionStatistics) {
+        let is_loaded = self
+            .model
+            .pgn_path
+            .as_ref()
+            .map_or(false, |path| path.display().to_string() == position.file);
+        if !is_loaded {
+            self.load_pgn_file(PathBuf::from(&position.file));
+            if self.model.pgn_path != Some(PathBuf::from(&position.file)) {
+                return;
+            }
+        }
+
+        let drill = self.model.games.get(position.game).and_then(|game| {
+            let node = find_position_node(&game.tree, position.node, &position.fen)?;
+            Some((node, game.tree.main_line_from(node).last().copied()))
+        });
+        let (node, line_end) = match drill {
+            Some(drill) => drill,
+            None => {
+                self.show_error(&format!(
+                    "Could not find this position in {} any more",
+                    position.file
+                ));
+                return;
+            }
+        };
+
+        self.model.selected_game = Some(position.game);
+        self.start_session();
+        self.chess_board.emit(ChessBoardMsg::GoToNode(node));
+        self.chess_board
+            .emit(ChessBoardMsg::SetPreferredLine(line_end));
+    }
+
+    fn explain_mistake(&mut self, guess: Guess) {
+        let analysis =
+            match MistakeAnalysis::new(&guess.fen, &guess.guessed_uci, &guess.expected_uci) {
+                Some(analysis) => analysis,
+                None => return,
+            };
+        if self.model.explanation_engine.is_none() {
+            self.model.explanation_engine = self.start_engine(None, WinMsg::ExplanationEvent);
+        }
+        if self.model.explanation_engine.is_some() {
+            self.explanation_label
+                .set_text("Wrong move, asking the engine why...");
+            self.model.mistake_analysis = Some(analysis);
+            self.search_next_mistake_line();
+        }
+    }
+
+    fn search_next_mistake_line(&mut self) {
+        let line = self.model.mistake_analysis.as_ref().and_then(|analysis| {
+            analysis
+                .move_to_search()
+                .map(|uci| (analysis.fen().to_string(), uci.clone()))
+        });
+        let (position_fen, uci) = match line {
+            Some(line) => line,
+            None => return,
+        };
+
+        let sent = self
+            .model
+            .explanation_engine
+            .as_mut()
+            .map(|engine| engine.search(&position_fen, &[uci], EXPLANATION_LIMIT));
+        if let Some(Err(error)) = sent {
+            self.model.explanation_engine = None;
+            self.model.mistake_analysis = None;
+            self.show_error(&error.to_string());
+        }
+    }
+
+    fn handle_explanation_event(&mut self, event: EngineEvent) {
+        match event {
+            EngineEvent::Info(info) => {
+                let is_latest_search = self
+                    .model
+                    .explanation_engine
+                    .as_ref()
+                    .map_or(false, |engine| engine.is_latest_search_running());
+                if let (true, Some(analysis)) =
+                    (is_latest_search, self.model.mistake_analysis.as_mut())
+                {
+                    analysis.record_info(&info);
+                }
+            }
+            EngineEvent::BestMove(_) => {
+                let latest_search = self
+                    .model
+                    .explanation_engine
+                    .as_mut()
+                    .map_or(false, |engine| engine.search_finished());
+                if !latest_search {
+                    return;
+                }
+                let complete = match self.model.mistake_analysis.as_mut() {
+                    Some(analysis) => {
+                        analysis.search_finished();
+                        analysis.is_complete()
+                    }
+                    None => return,
+                };
+                if complete {
+                    self.show_mistake_explanation();
+                } else {
+                    self.search_next_mistake_line();
+                }
+            }
+            EngineEvent::Terminated => {
+                self.model.explanation_engine = None;
+                self.model.mistake_analysis = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn show_mistake_explanation(&mut self) {
+        if let Some(analysis) = self.model.mistake_analysis.take() {
+            self.explanation_label.set_text(&analysis.description());
+            self.chess_board
+                .emit(ChessBoardMsg::SetEngineAnnotations(analysis.annotations()));
+        }
+    }
+
+    fn choose_pgn_file(&self) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Open pgn file"),
+            Some(&self.window),
+            gtk::FileChooserAction::Open,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Open", gtk::ResponseType::Accept),
+            ],
+        );
+        let pgn_filter = gtk::FileFilter::new();
+        pgn_filter.set_name(Some("Pgn files"));
+        pgn_filter.add_pattern("*.pgn");
+        dialog.add_filter(&pgn_filter);
+
+        let path = if dialog.run() == gtk::ResponseType::Accept {
+            dialog.get_filename()
+        } else {
+            None
+        };
+        dialog.destroy();
+
+        path
+    }
+
+    fn load_pgn_file(&mut self, path: PathBuf) {
+        let content = match fs::read(&path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(error) => {
+                self.show_error(&format!("Could not read {}: {}", path.display(), error));
+                return;
+            }
+        };
+
+        let (games, failures) = parse_games(&content);
+        if !failures.is_empty() {
+            let failures_text = failures
+                .iter()
+                .map(|(index, error)| format!("Game {}: {}", index + 1, error))
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.show_error(&format!(
+                "Skipped {} unreadable games of {}, they will be missing from a saved copy:\n{}",
+                failures.len(),
+                path.display(),
+                failures_text
+            ));
+            if games.is_empty() {
+                return;
+            }
+        }
+
+        self.model.games = games;
+        self.model.selected_game = None;
+        self.model.user_annotations.clear();
+        self.model.pgn_path = Some(path);
+        self.fill_games_store();
+    }
+
+    fn choose_pgn_save_file(&self) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Save pgn file"),
+            Some(&self.window),
+            gtk::FileChooserAction::Save,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Save", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        if let Some(pgn_path) = &self.model.pgn_path {
+            dialog.set_filename(pgn_path);
+        }
+
+        let path = if dialog.run() == gtk::ResponseType::Accept {
+            dialog.get_filename()
+        } else {
+            None
+        };
+        dialog.destroy();
+
+        path
+    }
+
+    fn choose_diagram_file(&self) -> Option<PathBuf> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Export position as png or svg"),
+            Some(&self.window),
+            gtk::FileChooserAction::Save,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Export", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name("position.png");
+        let images_filter = gtk::FileFilter::new();
+        images_filter.set_name(Some("Png or svg images"));
+        images_filter.add_pattern("*.png");
+        images_filter.add_pattern("*.svg");
+        dialog.add_filter(&images_filter);
+
+        let path = if dialog.run() == gtk::ResponseType::Accept {
+            dialog.get_filename()
+        } else {
+            None
+        };
+        dialog.destroy();
+
+        path
+    }
+
+    fn choose_animation_file(&self) -> Option<(PathBuf, AnimationSettings)> {
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Export line as animated gif"),
+            Some(&self.window),
+            gtk::FileChooserAction::Save,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Export", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name("line.gif");
+        let gif_filter = gtk::FileFilter::new();
+        gif_filter.set_name(Some("Gif animations"));
+        gif_filter.add_pattern("*.gif");
+        dialog.add_filter(&gif_filter);
+
+        let settings = self.model.animation_settings;
+        let delay_spin = gtk::SpinButton::new_with_range(100.0, 10000.0, 100.0);
+        delay_spin.set_value(settings.delay_ms as f64);
+        let captions_check = gtk::CheckButton::new_with_label("Show the moves below the board");
+        captions_check.set_active(settings.captions);
+        let options_box = gtk::Box::new(gtk::Orientation::Horizontal, 5);
+        options_box.pack_start(
+            &gtk::Label::new(Some("Delay per move (ms)")),
+            false,
+            false,
+            0,
+        );
+        options_box.pack_start(&delay_spin, false, false, 0);
+        options_box.pack_start(&captions_check, false, false, 0);
+        options_box.show_all();
+        dialog.set_extra_widget(&options_box);
+
+        let chosen = if dialog.run() == gtk::ResponseType::Accept {
+            dialog.get_filename().map(|path| {
+                let settings = AnimationSettings {
+                    delay_ms: delay_spin.get_value_as_int() as u32,
+                    captions: captions_check.get_active(),
+                };
+                (path, settings)
+            })
+        } else {
+            None
+        };
+        dialog.destroy();
+
+        chosen
+    }
+
+    fn save_pgn_file(&mut self, path: PathBuf) {
+        let mut games = self.model.games.clone();
+        for ((game_index, node), annotations) in &self.model.user_annotations {
+            if let Some(game) = games.get_mut(*game_index) {
+                game.tree
+                    .node_mut(*node)
+                    .annotations
+                    .merge(annotations.clone());
+            }
+        }
+
+        match fs::write(&path, write_games(&games)) {
+            Ok(()) => {
+                self.model.games = games;
+                self.model.user_annotations.clear();
+                self.model.pgn_path = Some(path);
+            }
+            Err(error) => {
+                self.show_error(&format!("Could not write {}: {}", path.display(), error));
+            }
+        }
+    }
+
+    fn fill_games_store(&self) {
+        self.model.games_store.clear();
+
+        for (game_index, game) in self.model.games.iter().enumerate() {
+            let headers = GAMES_HEADERS
+                .iter()
+                .map(|name| String::from(game.header(name).unwrap_or("?")))
+                .collect::<Vec<_>>();
+            let game_index = game_index as u32;
+
+            let mut values: Vec<&dyn ToValue> =
+                headers.iter().map(|value| value as &dyn ToValue).collect();
+            values.push(&game_index);
+            let columns = (0..=GAME_INDEX_COLUMN).collect::<Vec<_>>();
+
+            self.model
+                .games_store
+                .insert_with_values(None, &columns, &values);
+        }
+    }
+
+    fn selected_game_index(&self) -> Option<usize> {
+        self.games_view
+            .get_selection()
+            .get_selected()
+            .and_then(|(model, iter)| {
+                model
+                    .get_value(&iter, GAME_INDEX_COLUMN as i32)
+                    .get_some::<u32>()
+                    .ok()
+            })
+            .map(|index| index as usize)
+    }
+
+    fn start_session(&mut self) {
+        self.model.engine_request = None;
+        if let Some(engine) = self.model.engine.as_mut() {
+            let _ = engine.new_game();
+        }
+
+        let game = self
+            .model
+            .selected_game
+            .and_then(|game_index| self.model.games.get(game_index));
+        if let Some(game) = game {
+            self.move_list.emit(MoveListMsg::SetGame(game.tree.clone()));
+            self.chess_board.emit(ChessBoardMsg::StartSession(
+                game.clone(),
+                self.model.session_settings,
+            ));
+
+            let settings = self.model.session_settings;
+            let is_human = |color| {
+                let player = match color {
+                    Color::White => settings.white_player,
+                    Color::Black => settings.black_player,
+                };
+                player == PlayerKind::Human
+            };
+            let due_line = self
+                .model
+                .schedule
+                .most_due_line(&game.tree, is_human, now_timestamp());
+            self.chess_board
+                .emit(ChessBoardMsg::SetPreferredLine(due_line));
+        }
+    }
+
+    fn set_black_side(&mut self, black_side: BlackSide) {
+        self.model.black_side = black_side;
+        self.chess_board
+            .emit(ChessBoardMsg::SetBlackSide(black_side));
+        self.evaluation_bar
+            .emit(EvaluationBarMsg::SetBlackSide(black_side));
+    }
+
+    // The board is only turned when the human starts or stops playing Black alone, so
+    // that an orientation chosen by hand survives the other sides changes.
+    fn update_orientation_from_sides(&mut self, previous_settings: SessionSettings) {
+        let human_plays_black = only_black_is_human(self.model.session_settings);
+        if human_plays_black == only_black_is_human(previous_settings) {
+            return;
+        }
+
+        let new_black_side = if human_plays_black {
+            BlackSide::BlackBottom
+        } else {
+            BlackSide::BlackTop
+        };
+        if new_black_side != self.model.black_side {
+            self.set_black_side(new_black_side);
+        }
+    }
+
+    fn ask_session_settings(&self) -> Option<(SessionSettings, EngineSettings)> {
+        let dialog = gtk::Dialog::new_with_buttons(
+            Some("Configure sides"),
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            &[
+                ("_Cancel", gtk::ResponseType::Cancel),
+                ("_Start", gtk::ResponseType::Accept),
+            ],
+        );
+        let settings = self.model.session_settings;
+        let engine_settings = &self.model.engine_settings;
+
+        let grid = gtk::Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        grid.set_border_width(10);
+
+        let white_combo = build_player_kind_combo(settings.white_player);
+        let black_combo = build_player_kind_combo(settings.black_player);
+        let delay_spin = gtk::SpinButton::new_with_range(0.0, 10000.0, 100.0);
+        delay_spin.set_value(settings.computer_delay_ms as f64);
+
+        let engine_continuation_check =
+            gtk::CheckButton::new_with_label("Continue against the engine when the line ends");
+        engine_continuation_check.set_active(settings.engine_continuation);
+        let engine_path_button =
+            gtk::FileChooserButton::new("Choose a UCI engine", gtk::FileChooserAction::Open);
+        if let Some(path) = &engine_settings.path {
+            engine_path_button.set_filename(path);
+        }
+        let strength_combo = gtk::ComboBoxText::new();
+        for label in STRENGTH_LIMITS.iter() {
+            strength_combo.append_text(label);
+        }
+        let strength_index = match engine_settings.strength {
+            StrengthLimit::Elo(_) => 0,
+            StrengthLimit::Depth(_) => 1,
+            StrengthLimit::MoveTime(_) => 2,
+        };
+        strength_combo.set_active(Some(strength_index));
+        let strength_spin = gtk::SpinButton::new_with_range(1.0, 60000.0, 1.0);
+        strength_spin.set_value(engine_settings.strength.value() as f64);
+
+        grid.attach(&gtk::Label::new(Some("White")), 0, 0, 1, 1);
+        grid.attach(&white_combo, 1, 0, 1, 1);
+        grid.attach(&gtk::Label::new(Some("Black")), 0, 1, 1, 1);
+        grid.attach(&black_combo, 1, 1, 1, 1);
+        grid.attach(&gtk::Label::new(Some("Computer delay (ms)")), 0, 2, 1, 1);
+        grid.attach(&delay_spin, 1, 2, 1, 1);
+        grid.attach(&engine_continuation_check, 0, 3, 2, 1);
+        grid.attach(&gtk::Label::new(Some("Engine")), 0, 4, 1, 1);
+        grid.attach(&engine_path_button, 1, 4, 1, 1);
+        grid.attach(&strength_combo, 0, 5, 1, 1);
+        grid.attach(&strength_spin, 1, 5, 1, 1);
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+
+        let new_settings = if dialog.run() == gtk::ResponseType::Accept {
+            let strength_value = strength_spin.get_value_as_int() as u32;
+            let strength = match strength_combo.get_active() {
+                Some(1) => StrengthLimit::Depth(strength_value),
+                Some(2) => StrengthLimit::MoveTime(strength_value),
+                _ => StrengthLimit::Elo(strength_value),
+            };
+            Some((
+                SessionSettings {
+                    white_player: selected_player_kind(&white_combo),
+                    black_player: selected_player_kind(&black_combo),
+                    computer_delay_ms: delay_spin.get_value_as_int() as u32,
+                    engine_continuation: engine_continuation_check.get_active(),
+                },
+                EngineSettings {
+                    path: engine_path_button.get_filename(),
+                    strength,
+                },
+            ))
+        } else {
+            None
+        };
+        dialog.destroy();
+
+        new_settings
+    }
+
+    fn show_error(&self, message: &str) {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Ok,
+            message,
+        );
+        dialog.run();
+        dialog.destroy();
+    }
+}
+
+fn annotations_text(game: &PgnGame, node: Option<NodeId>) -> String {
+    let mut lines = game
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>();
+
+    if let Some(node) = node.map(|node| game.tree.node(node)) {
+        lines.push(String::new());
+        if let Some(comment) = &node.comment_before {
+            lines.push(format!("Before the move: {}", comment));
+        }
+        if node.move_played.is_some() {
+            let descriptions = node
+                .nags
+                .iter()
+                .filter_map(|nag| nag_description(*nag))
+                .collect::<Vec<_>>();
+            if descriptions.is_empty() {
+                lines.push(format!("Move: {}", node.san));
+            } else {
+                lines.push(format!("Move: {} ({})", node.san, descriptions.join(", ")));
+            }
+        }
+        if let Some(comment) = &node.comment_after {
+            lines.push(format!("After the move: {}", comment));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn game_title(game: &PgnGame) -> String {
+    format!(
+        "{} - {}, {}",
+        game.header("White").unwrap_or("?"),
+        game.header("Black").unwrap_or("?"),
+        game.header("Event").unwrap_or("?")
+    )
+}
+
+// The recorded node is tried first, in case the file has not changed since.
+fn find_position_node(
+    tree: &GameTree,
+    recorded_node: NodeId,
+    position_fen: &str,
+) -> Option<NodeId> {
+    let wanted = position_key(position_fen);
+    let is_wanted = |node: NodeId| position_key(&fen(&tree.node(node).position)) == wanted;
+
+    if recorded_node < tree.nodes_count() && is_wanted(recorded_node) {
+        Some(recorded_node)
+    } else {
+        (0..tree.nodes_count()).find(|node| is_wanted(*node))
+    }
+}
+
+fn only_black_is_human(settings: SessionSettings) -> bool {
+    settings.black_player == PlayerKind::Human && settings.white_player == PlayerKind::Computer
+}
+
+fn build_player_kind_combo(player_kind: PlayerKind) -> gtk::ComboBoxText {
+    let combo = gtk::ComboBoxText::new();
+    for (_, label) in PLAYER_KINDS.iter() {
+        combo.append_text(label);
+    }
+    let active_index = PLAYER_KINDS
+        .iter()
+        .position(|(kind, _)| *kind == player_kind)
+        .unwrap_or(0);
+    combo.set_active(Some(active_index as u32));
+
+    combo
+}
+
+fn selected_player_kind(combo: &gtk::ComboBoxText) -> PlayerKind {
+    combo
+        .get_active()
+        .and_then(|index| PLAYER_KINDS.get(index as usize))
+        .map_or(PlayerKind::Human, |(kind, _)| *kind)
+}
+
 pub fn start() {
     Win::run(()).expect("Failed to launch main window");
 }
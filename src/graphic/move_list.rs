@@ -0,0 +1,248 @@
+use gtk::prelude::*;
+use gtk::Inhibit;
+use relm::{Relm, Widget};
+use relm_derive::{widget, Msg};
+use shakmaty::{Color, Setup};
+
+use std::collections::HashSet;
+
+use crate::pgn::{nag_glyph, GameTree, NodeId};
+
+pub struct MoveListModel {
+    relm: Relm<MoveList>,
+    tree: Option<GameTree>,
+    revealed: HashSet<NodeId>,
+    reveal_all: bool,
+    current_node: Option<NodeId>,
+    moves_ranges: Vec<(i32, i32, NodeId)>,
+}
+
+#[derive(Msg)]
+pub enum MoveListMsg {
+    SetGame(GameTree),
+    SetCurrentNode(Option<NodeId>),
+    SetRevealAll(bool),
+    TextClicked(i32),
+    NodeClicked(NodeId),
+}
+
+#[widget]
+impl Widget for MoveList {
+    fn model(relm: &Relm<Self>, _: ()) -> MoveListModel {
+        MoveListModel {
+            relm: relm.clone(),
+            tree: None,
+            revealed: HashSet::new(),
+            reveal_all: false,
+            current_node: None,
+            moves_ranges: vec![],
+        }
+    }
+
+    fn update(&mut self, event: MoveListMsg) {
+        match event {
+            MoveListMsg::SetGame(tree) => {
+                self.model.tree = Some(tree);
+                self.model.revealed.clear();
+                self.model.current_node = None;
+                self.render();
+            }
+            MoveListMsg::SetCurrentNode(node) => {
+                self.model.current_node = node;
+                if let (Some(tree), Some(node)) = (&self.model.tree, node) {
+                    self.model.revealed.extend(tree.path_to(node));
+                }
+                self.render();
+            }
+            MoveListMsg::SetRevealAll(reveal_all) => {
+                self.model.reveal_all = reveal_all;
+                self.render();
+            }
+            MoveListMsg::TextClicked(offset) => {
+                let clicked_node = self
+                    .model
+                    .moves_ranges
+                    .iter()
+                    .find(|(start, end, _)| *start <= offset && offset < *end)
+                    .map(|(_, _, node)| *node);
+                if let Some(node) = clicked_node {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(MoveListMsg::NodeClicked(node));
+                }
+            }
+            MoveListMsg::NodeClicked(_) => {}
+        }
+    }
+
+    fn init_view(&mut self) {
+        self.setup_text_buffer();
+    }
+
+    view! {
+        gtk::Box(gtk::Orientation::Vertical, 5) {
+            gtk::CheckButton {
+                label: "Reveal all moves",
+                toggled(button) => MoveListMsg::SetRevealAll(button.get_active()),
+            },
+            gtk::ScrolledWindow {
+                min_content_width: 250,
+                child: {
+                    expand: true,
+                    fill: true,
+                },
+                #[name="moves_view"]
+                gtk::TextView {
+                    editable: false,
+                    cursor_visible: false,
+                    wrap_mode: gtk::WrapMode::Word,
+                    left_margin: 5,
+                    right_margin: 5,
+                    button_release_event(view, event) => (
+                        MoveListMsg::TextClicked(clicked_offset(view, event)),
+                        Inhibit(false)
+                    ),
+                },
+            },
+        }
+    }
+}
+
+impl MoveList {
+    fn setup_text_buffer(&self) {
+        if let Some(buffer) = self.moves_view.get_buffer() {
+            buffer.create_tag(Some("comment"), &[("foreground", &"#666666")]);
+            buffer.create_tag(Some("variation"), &[("foreground", &"#3050a0")]);
+            buffer.create_tag(Some("current"), &[("background", &"#ffd966")]);
+        }
+    }
+
+    fn is_revealed(&self, node: NodeId) -> bool {
+        self.model.reveal_all || self.model.revealed.contains(&node)
+    }
+
+    fn render(&mut self) {
+        let buffer = match self.moves_view.get_buffer() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        buffer.set_text("");
+        self.model.moves_ranges.clear();
+
+        if let Some(tree) = self.model.tree.take() {
+            self.render_continuation(&buffer, &tree, GameTree::ROOT, 0, true);
+            self.model.tree = Some(tree);
+        }
+
+        if let Some(current_node) = self.model.current_node {
+            let current_range = self
+                .model
+                .moves_ranges
+                .iter()
+                .find(|(_, _, node)| *node == current_node)
+                .copied();
+            if let Some((start, end, _)) = current_range {
+                buffer.apply_tag_by_name(
+                    "current",
+                    &buffer.get_iter_at_offset(start),
+                    &buffer.get_iter_at_offset(end),
+                );
+            }
+        }
+    }
+
+    fn render_continuation(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        tree: &GameTree,
+        parent: NodeId,
+        depth: usize,
+        force_move_number: bool,
+    ) {
+        let children = tree
+            .node(parent)
+            .children
+            .iter()
+            .copied()
+            .filter(|child| self.is_revealed(*child))
+            .collect::<Vec<_>>();
+        let (main_child, alternatives) = match children.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        self.render_move(buffer, tree, *main_child, depth, force_move_number);
+        for alternative in alternatives {
+            self.insert_text(buffer, "( ", Some("variation"));
+            self.render_move(buffer, tree, *alternative, depth + 1, true);
+            self.render_continuation(buffer, tree, *alternative, depth + 1, false);
+            self.insert_text(buffer, ") ", Some("variation"));
+        }
+        self.render_continuation(buffer, tree, *main_child, depth, !alternatives.is_empty());
+    }
+
+    fn render_move(
+        &mut self,
+        buffer: &gtk::TextBuffer,
+        tree: &GameTree,
+        node_id: NodeId,
+        depth: usize,
+        force_move_number: bool,
+    ) {
+        let node = tree.node(node_id);
+        let style = if depth > 0 { Some("variation") } else { None };
+
+        if let Some(comment) = &node.comment_before {
+            self.insert_text(buffer, &format!("{{{}}} ", comment), Some("comment"));
+        }
+
+        let position_before = node
+            .parent
+            .map(|parent| &tree.node(parent).position)
+            .unwrap_or(&node.position);
+        let move_number = position_before.fullmoves();
+        let move_text = match position_before.turn() {
+            Color::White => format!("{}. {}", move_number, node.san),
+            Color::Black if force_move_number => format!("{}... {}", move_number, node.san),
+            Color::Black => node.san.clone(),
+        };
+        let glyphs = node
+            .nags
+            .iter()
+            .filter_map(|nag| nag_glyph(*nag))
+            .collect::<String>();
+
+        let start = buffer.get_char_count();
+        self.insert_text(buffer, &format!("{}{}", move_text, glyphs), style);
+        let end = buffer.get_char_count();
+        self.model.moves_ranges.push((start, end, node_id));
+        self.insert_text(buffer, " ", None);
+
+        if let Some(comment) = &node.comment_after {
+            self.insert_text(buffer, &format!("{{{}}} ", comment), Some("comment"));
+        }
+    }
+
+    fn insert_text(&self, buffer: &gtk::TextBuffer, text: &str, tag_name: Option<&str>) {
+        let start = buffer.get_char_count();
+        buffer.insert(&mut buffer.get_end_iter(), text);
+
+        if let Some(tag_name) = tag_name {
+            buffer.apply_tag_by_name(
+                tag_name,
+                &buffer.get_iter_at_offset(start),
+                &buffer.get_end_iter(),
+            );
+        }
+    }
+}
+
+fn clicked_offset(view: &gtk::TextView, event: &gdk::EventButton) -> i32 {
+    let (x, y) = event.get_position();
+    let (buffer_x, buffer_y) =
+        view.window_to_buffer_coords(gtk::TextWindowType::Widget, x as i32, y as i32);
+
+    view.get_iter_at_location(buffer_x, buffer_y)
+        .map_or(-1, |iter| iter.get_offset())
+}
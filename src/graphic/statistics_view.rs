@@ -0,0 +1,154 @@
+use gtk::prelude::*;
+use relm::{Relm, Widget};
+use relm_derive::{widget, Msg};
+
+use super::chess_board::{render_thumbnail, BlackSide};
+use crate::progress::{move_san, Accuracy, PositionStatistics, Statistics};
+
+const THUMBNAIL_SIZE: u32 = 135;
+
+pub struct StatisticsViewModel {
+    relm: Relm<StatisticsView>,
+    positions: Vec<PositionStatistics>,
+}
+
+#[derive(Msg)]
+pub enum StatisticsViewMsg {
+    SetStatistics(Statistics),
+    PositionActivated(i32),
+    DrillRequested(PositionStatistics),
+}
+
+#[widget]
+impl Widget for StatisticsView {
+    fn model(relm: &Relm<Self>, _: ()) -> StatisticsViewModel {
+        StatisticsViewModel {
+            relm: relm.clone(),
+            positions: vec![],
+        }
+    }
+
+    fn update(&mut self, event: StatisticsViewMsg) {
+        match event {
+            StatisticsViewMsg::SetStatistics(statistics) => {
+                self.games_label
+                    .set_text(&accuracies_text(&statistics.per_game));
+                self.openings_label
+                    .set_text(&accuracies_text(&statistics.per_eco));
+                self.model.positions = statistics.worst_positions;
+                self.fill_positions_list();
+            }
+            StatisticsViewMsg::PositionActivated(row_index) => {
+                let position = self.model.positions.get(row_index as usize).cloned();
+                if let Some(position) = position {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(StatisticsViewMsg::DrillRequested(position));
+                }
+            }
+            StatisticsViewMsg::DrillRequested(_) => {}
+        }
+    }
+
+    view! {
+        gtk::ScrolledWindow {
+            min_content_width: 450,
+            gtk::Box(gtk::Orientation::Vertical, 5) {
+                gtk::Label {
+                    markup: "<b>Accuracy per game</b>",
+                    xalign: 0.0,
+                },
+                #[name="games_label"]
+                gtk::Label {
+                    text: "",
+                    selectable: true,
+                    xalign: 0.0,
+                },
+                gtk::Label {
+                    markup: "<b>Accuracy per opening</b>",
+                    xalign: 0.0,
+                },
+                #[name="openings_label"]
+                gtk::Label {
+                    text: "",
+                    selectable: true,
+                    xalign: 0.0,
+                },
+                gtk::Label {
+                    markup: "<b>Weakest positions</b> (click one to drill it)",
+                    xalign: 0.0,
+                },
+                #[name="positions_list"]
+                gtk::ListBox {
+                    row_activated(_, row) => StatisticsViewMsg::PositionActivated(row.get_index()),
+                },
+            },
+        }
+    }
+}
+
+impl StatisticsView {
+    fn fill_positions_list(&self) {
+        for row in self.positions_list.get_children() {
+            self.positions_list.remove(&row);
+        }
+
+        for position in &self.model.positions {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 10);
+            // The side to move sits at the bottom, as during the revision.
+            let black_side = if position.fen.split_whitespace().nth(1) == Some("b") {
+                BlackSide::BlackBottom
+            } else {
+                BlackSide::BlackTop
+            };
+            if let Some(thumbnail) = render_thumbnail(&position.fen, THUMBNAIL_SIZE, black_side) {
+                row.pack_start(
+                    &gtk::Image::new_from_surface(Some(&*thumbnail)),
+                    false,
+                    false,
+                    0,
+                );
+            }
+
+            let description = gtk::Label::new(Some(&position_text(position)));
+            description.set_xalign(0.0);
+            row.pack_start(&description, true, true, 0);
+
+            self.positions_list.add(&row);
+        }
+
+        self.positions_list.show_all();
+    }
+}
+
+fn accuracies_text(accuracies: &[Accuracy]) -> String {
+    if accuracies.is_empty() {
+        return "No attempt recorded yet".to_string();
+    }
+
+    accuracies
+        .iter()
+        .map(|accuracy| {
+            format!(
+                "{}: {}/{} correct ({:.0}%)",
+                accuracy.label,
+                accuracy.successes,
+                accuracy.attempts,
+                accuracy.success_rate() * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn position_text(position: &PositionStatistics) -> String {
+    format!(
+        "{}\nExpected move: {}\n{}/{} correct ({:.0}%)",
+        position.accuracy.label,
+        move_san(&position.fen, &position.expected_move),
+        position.accuracy.successes,
+        position.accuracy.attempts,
+        position.accuracy.success_rate() * 100.0
+    )
+}
@@ -1,4 +1,10 @@
+mod engine;
 mod graphic;
+mod pgn;
+mod progress;
+mod reviser;
+mod scheduler;
+mod storage;
 
 fn main() {
     graphic::start();
@@ -0,0 +1,10 @@
+mod game_tree;
+mod graphical_annotations;
+mod nag;
+mod parser;
+mod writer;
+pub use game_tree::*;
+pub use graphical_annotations::*;
+pub use nag::*;
+pub use parser::*;
+pub use writer::*;
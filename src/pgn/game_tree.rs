@@ -0,0 +1,120 @@
+use shakmaty::{Chess, Move};
+
+use super::graphical_annotations::GraphicalAnnotations;
+
+pub type NodeId = usize;
+
+#[derive(Clone)]
+pub struct MoveNode {
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    pub move_played: Option<Move>,
+    pub san: String,
+    pub position: Chess,
+    pub comment_before: Option<String>,
+    pub comment_after: Option<String>,
+    pub nags: Vec<u8>,
+    pub annotations: GraphicalAnnotations,
+}
+
+impl MoveNode {
+    fn root(position: Chess) -> Self {
+        MoveNode {
+            parent: None,
+            children: vec![],
+            move_played: None,
+            san: String::new(),
+            position,
+            comment_before: None,
+            comment_after: None,
+            nags: vec![],
+            annotations: GraphicalAnnotations::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GameTree {
+    nodes: Vec<MoveNode>,
+}
+
+impl GameTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new(start_position: Chess) -> Self {
+        GameTree {
+            nodes: vec![MoveNode::root(start_position)],
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &MoveNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut MoveNode {
+        &mut self.nodes[id]
+    }
+
+    pub fn nodes_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn add_child(
+        &mut self,
+        parent: NodeId,
+        move_played: Move,
+        san: String,
+        position: Chess,
+    ) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(MoveNode {
+            parent: Some(parent),
+            children: vec![],
+            move_played: Some(move_played),
+            san,
+            position,
+            comment_before: None,
+            comment_after: None,
+            nags: vec![],
+            annotations: GraphicalAnnotations::default(),
+        });
+        self.nodes[parent].children.push(id);
+
+        id
+    }
+
+    pub fn main_line_child(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].children.get(0).copied()
+    }
+
+    pub fn main_line(&self) -> Vec<NodeId> {
+        self.main_line_from(GameTree::ROOT)
+    }
+
+    pub fn main_line_from(&self, id: NodeId) -> Vec<NodeId> {
+        let mut line = vec![];
+        let mut current = id;
+        while let Some(child) = self.main_line_child(current) {
+            line.push(child);
+            current = child;
+        }
+
+        line
+    }
+
+    pub fn path_to(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = vec![];
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(current);
+            current = parent;
+        }
+        path.reverse();
+
+        path
+    }
+
+    pub fn ply_of(&self, id: NodeId) -> usize {
+        self.path_to(id).len()
+    }
+}
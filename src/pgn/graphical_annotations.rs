@@ -0,0 +1,179 @@
+use shakmaty::Square;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    pub fn from_char(color: char) -> Option<Self> {
+        match color {
+            'G' => Some(AnnotationColor::Green),
+            'R' => Some(AnnotationColor::Red),
+            'Y' => Some(AnnotationColor::Yellow),
+            'B' => Some(AnnotationColor::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn char(self) -> char {
+        match self {
+            AnnotationColor::Green => 'G',
+            AnnotationColor::Red => 'R',
+            AnnotationColor::Yellow => 'Y',
+            AnnotationColor::Blue => 'B',
+        }
+    }
+
+    pub fn rgb(self) -> (f64, f64, f64) {
+        match self {
+            AnnotationColor::Green => (0.08, 0.47, 0.11),
+            AnnotationColor::Red => (0.53, 0.13, 0.11),
+            AnnotationColor::Yellow => (0.90, 0.63, 0.05),
+            AnnotationColor::Blue => (0.0, 0.19, 0.53),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SquareHighlight {
+    pub square: Square,
+    pub color: AnnotationColor,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ArrowAnnotation {
+    pub from: Square,
+    pub to: Square,
+    pub color: AnnotationColor,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GraphicalAnnotations {
+    pub squares: Vec<SquareHighlight>,
+    pub arrows: Vec<ArrowAnnotation>,
+}
+
+impl GraphicalAnnotations {
+    pub fn is_empty(&self) -> bool {
+        self.squares.is_empty() && self.arrows.is_empty()
+    }
+
+    pub fn merge(&mut self, other: GraphicalAnnotations) {
+        for highlight in other.squares {
+            if !self.squares.contains(&highlight) {
+                self.squares.push(highlight);
+            }
+        }
+        for arrow in other.arrows {
+            if !self.arrows.contains(&arrow) {
+                self.arrows.push(arrow);
+            }
+        }
+    }
+
+    pub fn toggle_square(&mut self, square: Square, color: AnnotationColor) {
+        let existing = self
+            .squares
+            .iter()
+            .position(|highlight| highlight.square == square);
+        if let Some(index) = existing {
+            let removed = self.squares.remove(index);
+            if removed.color == color {
+                return;
+            }
+        }
+        self.squares.push(SquareHighlight { square, color });
+    }
+
+    pub fn toggle_arrow(&mut self, from: Square, to: Square, color: AnnotationColor) {
+        let existing = self
+            .arrows
+            .iter()
+            .position(|arrow| arrow.from == from && arrow.to == to);
+        if let Some(index) = existing {
+            let removed = self.arrows.remove(index);
+            if removed.color == color {
+                return;
+            }
+        }
+        self.arrows.push(ArrowAnnotation { from, to, color });
+    }
+
+    pub fn to_commands(&self) -> String {
+        let mut commands = String::new();
+        if !self.squares.is_empty() {
+            let squares = self
+                .squares
+                .iter()
+                .map(|highlight| format!("{}{}", highlight.color.char(), highlight.square))
+                .collect::<Vec<_>>();
+            commands.push_str(&format!("[%csl {}]", squares.join(",")));
+        }
+        if !self.arrows.is_empty() {
+            let arrows = self
+                .arrows
+                .iter()
+                .map(|arrow| format!("{}{}{}", arrow.color.char(), arrow.from, arrow.to))
+                .collect::<Vec<_>>();
+            commands.push_str(&format!("[%cal {}]", arrows.join(",")));
+        }
+
+        commands
+    }
+}
+
+pub fn extract_graphical_annotations(comment: &str) -> (String, GraphicalAnnotations) {
+    let mut annotations = GraphicalAnnotations::default();
+    let mut text = String::new();
+    let mut remaining = comment;
+
+    while let Some(command_start) = remaining.find("[%") {
+        text.push_str(&remaining[..command_start]);
+        let command = &remaining[command_start + 2..];
+        let command_end = command.find(']').unwrap_or_else(|| command.len());
+        parse_command(&command[..command_end], &mut annotations);
+        remaining = command.get(command_end + 1..).unwrap_or("");
+    }
+    text.push_str(remaining);
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (text, annotations)
+}
+
+fn parse_command(command: &str, annotations: &mut GraphicalAnnotations) {
+    let mut parts = command.trim().splitn(2, char::is_whitespace);
+    let name = parts.next().unwrap_or("");
+    let items = parts
+        .next()
+        .unwrap_or("")
+        .split(',')
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty());
+
+    match name {
+        "csl" => annotations
+            .squares
+            .extend(items.filter_map(parse_square_highlight)),
+        "cal" => annotations.arrows.extend(items.filter_map(parse_arrow)),
+        _ => {}
+    }
+}
+
+fn parse_square_highlight(item: &str) -> Option<SquareHighlight> {
+    let color = AnnotationColor::from_char(item.chars().next()?)?;
+    let square = item.get(1..3)?.parse::<Square>().ok()?;
+
+    Some(SquareHighlight { square, color })
+}
+
+fn parse_arrow(item: &str) -> Option<ArrowAnnotation> {
+    let color = AnnotationColor::from_char(item.chars().next()?)?;
+    let from = item.get(1..3)?.parse::<Square>().ok()?;
+    let to = item.get(3..5)?.parse::<Square>().ok()?;
+
+    Some(ArrowAnnotation { from, to, color })
+}
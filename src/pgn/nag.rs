@@ -0,0 +1,58 @@
+pub fn nag_glyph(nag: u8) -> Option<&'static str> {
+    match nag {
+        1 => Some("!"),
+        2 => Some("?"),
+        3 => Some("!!"),
+        4 => Some("??"),
+        5 => Some("!?"),
+        6 => Some("?!"),
+        7 => Some("□"),
+        10 => Some("="),
+        13 => Some("∞"),
+        14 => Some("+="),
+        15 => Some("=+"),
+        16 => Some("+/-"),
+        17 => Some("-/+"),
+        18 => Some("+-"),
+        19 => Some("-+"),
+        22 | 23 => Some("⨀"),
+        32 | 33 => Some("⟳"),
+        36 | 37 => Some("→"),
+        40 | 41 => Some("↑"),
+        132 | 133 => Some("⇆"),
+        146 => Some("N"),
+        _ => None,
+    }
+}
+
+pub fn nag_description(nag: u8) -> Option<&'static str> {
+    match nag {
+        1 => Some("good move"),
+        2 => Some("mistake"),
+        3 => Some("brilliant move"),
+        4 => Some("blunder"),
+        5 => Some("interesting move"),
+        6 => Some("dubious move"),
+        7 => Some("forced move"),
+        10 => Some("equal position"),
+        13 => Some("unclear position"),
+        14 => Some("White is slightly better"),
+        15 => Some("Black is slightly better"),
+        16 => Some("White is better"),
+        17 => Some("Black is better"),
+        18 => Some("White is winning"),
+        19 => Some("Black is winning"),
+        22 => Some("White is in zugzwang"),
+        23 => Some("Black is in zugzwang"),
+        32 => Some("White has a development advantage"),
+        33 => Some("Black has a development advantage"),
+        36 => Some("White has the initiative"),
+        37 => Some("Black has the initiative"),
+        40 => Some("White has the attack"),
+        41 => Some("Black has the attack"),
+        132 => Some("White has counterplay"),
+        133 => Some("Black has counterplay"),
+        146 => Some("novelty"),
+        _ => None,
+    }
+}
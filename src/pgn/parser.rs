@@ -0,0 +1,563 @@
+use failure::Fail;
+use shakmaty::fen::Fen;
+use shakmaty::san::{San, SanPlus};
+use shakmaty::{Chess, Position};
+
+use super::game_tree::*;
+use super::graphical_annotations::{extract_graphical_annotations, GraphicalAnnotations};
+
+#[derive(Debug, Fail)]
+pub enum PgnError {
+    #[fail(display = "Malformed header: {}", line)]
+    MalformedHeader { line: String },
+    #[fail(display = "Bad starting position fen: {}", fen)]
+    BadStartingFen { fen: String },
+    #[fail(display = "Illegal or unreadable move {} after ply {}", san, ply)]
+    IllegalMove { san: String, ply: usize },
+    #[fail(display = "Variation opened before any move")]
+    VariationWithoutMove,
+    #[fail(display = "Unbalanced variation parenthesis")]
+    UnbalancedVariation,
+}
+
+#[derive(Clone)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    pub tree: GameTree,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Move(String),
+    Comment(String),
+    Nag(u8),
+    StartVariation,
+    EndVariation,
+    GameResult(String),
+}
+
+pub fn position_from_fen(fen: &str) -> Option<Chess> {
+    Fen::from_ascii(fen.trim().as_bytes())
+        .ok()
+        .and_then(|fen| fen.position().ok())
+}
+
+/// The fen without its move counters: the same position reached at another
+/// move number gives the same key.
+pub fn position_key(fen: &str) -> String {
+    fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse_game(text: &str) -> Result<PgnGame, PgnError> {
+    let (headers, movetext) = split_headers(text)?;
+
+    let start_position = match headers.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => {
+            position_from_fen(fen).ok_or_else(|| PgnError::BadStartingFen { fen: fen.clone() })?
+        }
+        None => Chess::default(),
+    };
+
+    let tokens = tokenize(movetext);
+    let (tree, result) = build_tree(start_position, tokens)?;
+    let result = result
+        .or_else(|| {
+            headers
+                .iter()
+                .find(|(name, _)| name == "Result")
+                .map(|(_, value)| value.clone())
+        })
+        .unwrap_or_else(|| String::from("*"));
+
+    Ok(PgnGame {
+        headers,
+        tree,
+        result,
+    })
+}
+
+fn split_headers(text: &str) -> Result<(Vec<(String, String)>, &str), PgnError> {
+    let mut headers = vec![];
+    let mut movetext_start = text.len();
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if is_header_line(trimmed) {
+            headers.push(parse_header_line(trimmed)?);
+        } else if !trimmed.is_empty() {
+            movetext_start = offset;
+            break;
+        }
+        offset += line.len();
+    }
+
+    Ok((headers, &text[movetext_start..]))
+}
+
+fn parse_header_line(line: &str) -> Result<(String, String), PgnError> {
+    let malformed = || PgnError::MalformedHeader {
+        line: String::from(line),
+    };
+
+    let content = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or_else(malformed)?
+        .trim();
+    let name_end = content.find(char::is_whitespace).ok_or_else(malformed)?;
+    let name = &content[..name_end];
+    let value = content[name_end..].trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(malformed)?;
+    let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+
+    Ok((String::from(name), value))
+}
+
+fn tokenize(movetext: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = movetext.chars().peekable();
+
+    while let Some(current) = chars.next() {
+        match current {
+            '{' => {
+                let comment = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                tokens.push(Token::Comment(String::from(comment.trim())));
+            }
+            ';' => {
+                let comment = chars
+                    .by_ref()
+                    .take_while(|c| *c != '\n')
+                    .collect::<String>();
+                tokens.push(Token::Comment(String::from(comment.trim())));
+            }
+            '%' => {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+            }
+            '(' => tokens.push(Token::StartVariation),
+            ')' => tokens.push(Token::EndVariation),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(*digit);
+                    chars.next();
+                }
+                if let Ok(nag) = digits.parse::<u8>() {
+                    tokens.push(Token::Nag(nag));
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = String::new();
+                word.push(c);
+                while let Some(next) = chars
+                    .peek()
+                    .filter(|c| !c.is_whitespace() && !"{}();$".contains(**c))
+                {
+                    word.push(*next);
+                    chars.next();
+                }
+                push_word_tokens(&word, &mut tokens);
+            }
+        }
+    }
+
+    tokens
+}
+
+fn push_word_tokens(word: &str, tokens: &mut Vec<Token>) {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+        tokens.push(Token::GameResult(String::from(word)));
+        return;
+    }
+
+    let word = strip_move_number(word);
+    let san_end = word
+        .find(|c| c == '!' || c == '?')
+        .unwrap_or_else(|| word.len());
+    let (san, suffix) = word.split_at(san_end);
+
+    if !san.is_empty() {
+        tokens.push(Token::Move(String::from(san)));
+    }
+    if let Some(nag) = suffix_to_nag(suffix) {
+        tokens.push(Token::Nag(nag));
+    }
+}
+
+// The move number may be glued to the move, as in "12.Nf3" or "12...0-0".
+fn strip_move_number(word: &str) -> &str {
+    let digits_end = word
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| word.len());
+    let after_digits = &word[digits_end..];
+    if after_digits.starts_with('.') {
+        after_digits.trim_start_matches('.')
+    } else {
+        word
+    }
+}
+
+fn suffix_to_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn build_tree(
+    start_position: Chess,
+    tokens: Vec<Token>,
+) -> Result<(GameTree, Option<String>), PgnError> {
+    let mut tree = GameTree::new(start_position);
+    let mut current = GameTree::ROOT;
+    let mut variations_stack = vec![];
+    let mut pending_comment: Option<String> = None;
+    let mut after_move = false;
+    let mut result = None;
+
+    for token in tokens {
+        match token {
+            Token::Move(san_text) => {
+                current = add_move(&mut tree, current, &san_text)?;
+                tree.node_mut(current).comment_before = pending_comment.take();
+                after_move = true;
+            }
+            Token::Comment(comment) => {
+                if after_move {
+                    let node = tree.node_mut(current);
+                    node.comment_after = Some(join_comments(node.comment_after.take(), comment));
+                } else {
+                    pending_comment = Some(join_comments(pending_comment.take(), comment));
+                }
+            }
+            Token::Nag(nag) => {
+                if after_move {
+                    tree.node_mut(current).nags.push(nag);
+                }
+            }
+            Token::StartVariation => {
+                let parent = tree
+                    .node(current)
+                    .parent
+                    .ok_or(PgnError::VariationWithoutMove)?;
+                variations_stack.push(current);
+                current = parent;
+                after_move = false;
+            }
+            Token::EndVariation => {
+                current = variations_stack
+                    .pop()
+                    .ok_or(PgnError::UnbalancedVariation)?;
+                after_move = true;
+            }
+            Token::GameResult(value) => {
+                result = Some(value);
+                break;
+            }
+        }
+    }
+
+    if !variations_stack.is_empty() {
+        return Err(PgnError::UnbalancedVariation);
+    }
+
+    if let Some(comment) = pending_comment {
+        let node = tree.node_mut(current);
+        node.comment_after = Some(join_comments(node.comment_after.take(), comment));
+    }
+
+    for node_id in 0..tree.nodes_count() {
+        let before_annotations = extract_node_annotations(tree.node_mut(node_id));
+        // Drawn before the move, they describe the position the move is played from.
+        let parent = tree.node(node_id).parent.unwrap_or(node_id);
+        tree.node_mut(parent).annotations.merge(before_annotations);
+    }
+
+    Ok((tree, result))
+}
+
+/// Keeps the annotations of the comment after the move, and gives back those found before it.
+fn extract_node_annotations(node: &mut MoveNode) -> GraphicalAnnotations {
+    let (comment_before, before_annotations) = strip_comment(node.comment_before.take());
+    let (comment_after, after_annotations) = strip_comment(node.comment_after.take());
+
+    node.comment_before = comment_before;
+    node.comment_after = comment_after;
+    node.annotations.merge(after_annotations);
+
+    before_annotations
+}
+
+fn strip_comment(comment: Option<String>) -> (Option<String>, GraphicalAnnotations) {
+    match comment {
+        Some(comment) => {
+            let (text, annotations) = extract_graphical_annotations(&comment);
+            let text = if text.is_empty() { None } else { Some(text) };
+            (text, annotations)
+        }
+        None => (None, GraphicalAnnotations::default()),
+    }
+}
+
+fn add_move(tree: &mut GameTree, parent: NodeId, san_text: &str) -> Result<NodeId, PgnError> {
+    let illegal_move = || PgnError::IllegalMove {
+        san: String::from(san_text),
+        ply: tree.ply_of(parent),
+    };
+
+    let normalized = san_text
+        .trim_end_matches(|c| c == '+' || c == '#')
+        .replace('0', "O");
+    let position = &tree.node(parent).position;
+    let san = San::from_ascii(normalized.as_bytes()).map_err(|_| illegal_move())?;
+    let move_played = san.to_move(position).map_err(|_| illegal_move())?;
+
+    let san_plus = SanPlus::from_move(position.clone(), &move_played).to_string();
+    let mut next_position = position.clone();
+    next_position.play_unchecked(&move_played);
+
+    Ok(tree.add_child(parent, move_played, san_plus, next_position))
+}
+
+fn join_comments(previous: Option<String>, comment: String) -> String {
+    match previous {
+        Some(previous) => format!("{} {}", previous, comment),
+        None => comment,
+    }
+}
+
+/// Parses every game on its own: gives the readable games, and the index and error of the others.
+pub fn parse_games(text: &str) -> (Vec<PgnGame>, Vec<(usize, PgnError)>) {
+    let mut games = vec![];
+    let mut failures = vec![];
+
+    for (index, game_text) in split_games(text).into_iter().enumerate() {
+        match parse_game(game_text) {
+            Ok(game) => games.push(game),
+            Err(error) => failures.push((index, error)),
+        }
+    }
+
+    (games, failures)
+}
+
+fn split_games(text: &str) -> Vec<&str> {
+    let mut games = vec![];
+    let mut game_start = 0;
+    let mut in_movetext = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if is_header_line(trimmed) {
+            if in_movetext {
+                games.push(&text[game_start..offset]);
+                game_start = offset;
+                in_movetext = false;
+            }
+        } else if !trimmed.is_empty() {
+            in_movetext = true;
+        }
+        offset += line.len();
+    }
+
+    if !text[game_start..].trim().is_empty() {
+        games.push(&text[game_start..]);
+    }
+
+    games
+}
+
+fn is_header_line(line: &str) -> bool {
+    line.starts_with('[')
+        && line
+            .chars()
+            .nth(1)
+            .map_or(false, |c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::fen::fen;
+
+    fn sans(tree: &GameTree, nodes: &[NodeId]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|node| tree.node(*node).san.clone())
+            .collect()
+    }
+
+    fn children_sans(tree: &GameTree, node: NodeId) -> Vec<String> {
+        sans(tree, &tree.node(node).children)
+    }
+
+    #[test]
+    fn reads_nested_variations() {
+        let game = parse_game("1. e4 e5 (1... c5 2. Nf3 (2. Nc3 Nc6) 2... d6) 2. Nf3 *").unwrap();
+        let tree = &game.tree;
+        let main_line = tree.main_line();
+
+        assert_eq!(sans(tree, &main_line), vec!["e4", "e5", "Nf3"]);
+        assert_eq!(children_sans(tree, main_line[0]), vec!["e5", "c5"]);
+        let c5 = tree.node(main_line[0]).children[1];
+        assert_eq!(children_sans(tree, c5), vec!["Nf3", "Nc3"]);
+        assert_eq!(sans(tree, &tree.main_line_from(c5)), vec!["Nf3", "d6"]);
+        let nc3 = tree.node(c5).children[1];
+        assert_eq!(sans(tree, &tree.main_line_from(nc3)), vec!["Nc6"]);
+    }
+
+    #[test]
+    fn reads_brace_and_line_comments() {
+        let game =
+            parse_game("{Opening} 1. e4 {best by test} e5 ; a line comment\n2. Nf3 *").unwrap();
+        let tree = &game.tree;
+        let main_line = tree.main_line();
+        let (e4, e5, nf3) = (main_line[0], main_line[1], main_line[2]);
+
+        assert_eq!(tree.node(e4).comment_before.as_deref(), Some("Opening"));
+        assert_eq!(tree.node(e4).comment_after.as_deref(), Some("best by test"));
+        assert_eq!(
+            tree.node(e5).comment_after.as_deref(),
+            Some("a line comment")
+        );
+        assert_eq!(tree.node(nf3).comment_before, None);
+        assert_eq!(tree.node(nf3).comment_after, None);
+    }
+
+    #[test]
+    fn reads_nags_and_move_suffixes() {
+        let game = parse_game("1. e4 $1 e5 $2 $14 2. Nf3!? Nc6?? *").unwrap();
+        let tree = &game.tree;
+        let nags = tree
+            .main_line()
+            .iter()
+            .map(|node| tree.node(*node).nags.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(nags, vec![vec![1], vec![2, 14], vec![5], vec![4]]);
+    }
+
+    #[test]
+    fn reads_castling_written_with_zeros() {
+        let game = parse_game(
+            "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 d6 5. d3 Bg4 6. Nc3 Qd7 7.Be3 7...0-0-0 *",
+        )
+        .unwrap();
+        let tree = &game.tree;
+        let main_line = tree.main_line();
+
+        assert_eq!(main_line.len(), 14);
+        assert_eq!(tree.node(main_line[6]).san, "O-O");
+        assert_eq!(tree.node(main_line[12]).san, "Be3");
+        assert_eq!(tree.node(main_line[13]).san, "O-O-O");
+    }
+
+    #[test]
+    fn reads_each_result_token() {
+        for result in &["1-0", "0-1", "1/2-1/2", "*"] {
+            let game = parse_game(&format!("1. e4 e5 {}", result)).unwrap();
+
+            assert_eq!(&game.result, result);
+            assert_eq!(game.tree.main_line().len(), 2);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_result_header() {
+        let game = parse_game("[Result \"0-1\"]\n\n1. e4 e5").unwrap();
+        assert_eq!(game.result, "0-1");
+
+        let game = parse_game("1. e4 e5").unwrap();
+        assert_eq!(game.result, "*");
+    }
+
+    #[test]
+    fn starts_from_the_fen_header() {
+        let start_fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let text = format!(
+            "[SetUp \"1\"]\n[FEN \"{}\"]\n\n1. e4 Kd7 2. Kd2 *",
+            start_fen
+        );
+        let game = parse_game(&text).unwrap();
+
+        assert_eq!(game.header("SetUp"), Some("1"));
+        assert_eq!(fen(&game.tree.node(GameTree::ROOT).position), start_fen);
+        assert_eq!(
+            sans(&game.tree, &game.tree.main_line()),
+            vec!["e4", "Kd7", "Kd2"]
+        );
+    }
+
+    #[test]
+    fn reports_a_bad_starting_fen() {
+        match parse_game("[FEN \"not a position\"]\n\n1. e4 *") {
+            Err(PgnError::BadStartingFen { fen: bad_fen }) => {
+                assert_eq!(bad_fen, "not a position")
+            }
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("the game should not be read"),
+        }
+    }
+
+    #[test]
+    fn reports_the_ply_of_an_illegal_move() {
+        match parse_game("1. e4 e5 2. Nf3 Nf6 3. Ke3 *") {
+            Err(PgnError::IllegalMove { san, ply }) => {
+                assert_eq!(san, "Ke3");
+                assert_eq!(ply, 4);
+            }
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("the game should not be read"),
+        }
+    }
+
+    #[test]
+    fn skips_the_unreadable_games() {
+        let text = "[Event \"First\"]\n\n1. e4 e5 *\n\n\
+                    [Event \"Second\"]\n\n1. e4 e5 2. Ke3 *\n\n\
+                    [Event \"Third\"]\n\n1. d4 d5 *\n";
+        let (games, failures) = parse_games(text);
+
+        let events = games
+            .iter()
+            .map(|game| game.header("Event"))
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec![Some("First"), Some("Third")]);
+        assert_eq!(failures.len(), 1);
+        match &failures[0] {
+            (1, PgnError::IllegalMove { san, ply }) => {
+                assert_eq!(san, "Ke3");
+                assert_eq!(*ply, 2);
+            }
+            (index, error) => panic!("unexpected failure of game {}: {}", index, error),
+        }
+    }
+
+    #[test]
+    fn position_key_leaves_out_the_move_counters() {
+        assert_eq!(
+            position_key("4k3/8/8/8/8/8/4P3/4K3 w - - 12 40"),
+            "4k3/8/8/8/8/8/4P3/4K3 w - -"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use shakmaty::{Color, Setup};
+
+use super::game_tree::*;
+use super::parser::PgnGame;
+
+const MAX_LINE_LENGTH: usize = 80;
+
+pub fn write_games(games: &[PgnGame]) -> String {
+    games.iter().map(write_game).collect::<Vec<_>>().join("\n")
+}
+
+pub fn write_game(game: &PgnGame) -> String {
+    let mut text = String::new();
+    for (name, value) in &game.headers {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        text.push_str(&format!("[{} \"{}\"]\n", name, value));
+    }
+    text.push('\n');
+
+    let mut tokens = vec![];
+    if let Some(comment) = node_comment_after(game.tree.node(GameTree::ROOT)) {
+        tokens.push(comment);
+    }
+    write_continuation(&game.tree, GameTree::ROOT, true, &mut tokens);
+    tokens.push(game.result.clone());
+
+    text.push_str(&wrap_tokens(&tokens));
+    text.push('\n');
+
+    text
+}
+
+fn write_continuation(
+    tree: &GameTree,
+    parent: NodeId,
+    force_move_number: bool,
+    tokens: &mut Vec<String>,
+) {
+    let children = &tree.node(parent).children;
+    let (main_child, alternatives) = match children.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+
+    write_move(tree, *main_child, force_move_number, tokens);
+    for alternative in alternatives {
+        tokens.push(String::from("("));
+        write_move(tree, *alternative, true, tokens);
+        write_continuation(tree, *alternative, false, tokens);
+        tokens.push(String::from(")"));
+    }
+    write_continuation(tree, *main_child, !alternatives.is_empty(), tokens);
+}
+
+fn write_move(tree: &GameTree, node_id: NodeId, force_move_number: bool, tokens: &mut Vec<String>) {
+    let node = tree.node(node_id);
+
+    if let Some(comment) = &node.comment_before {
+        tokens.push(format!("{{{}}}", comment));
+    }
+
+    if let Some(parent) = node.parent {
+        let position_before = &tree.node(parent).position;
+        let move_number = position_before.fullmoves();
+        match position_before.turn() {
+            Color::White => tokens.push(format!("{}.", move_number)),
+            Color::Black if force_move_number => tokens.push(format!("{}...", move_number)),
+            Color::Black => {}
+        }
+    }
+
+    tokens.push(node.san.clone());
+    for nag in &node.nags {
+        tokens.push(format!("${}", nag));
+    }
+
+    if let Some(comment) = node_comment_after(node) {
+        tokens.push(comment);
+    }
+}
+
+// Annotations always describe the position reached by the node: the parser moves
+// those found before a move to its parent.
+fn node_comment_after(node: &MoveNode) -> Option<String> {
+    let commands = node.annotations.to_commands();
+    let comment = match (&node.comment_after, commands.is_empty()) {
+        (Some(comment), true) => comment.clone(),
+        (Some(comment), false) => format!("{} {}", commands, comment),
+        (None, false) => commands,
+        (None, true) => return None,
+    };
+
+    Some(format!("{{{}}}", comment))
+}
+
+fn wrap_tokens(tokens: &[String]) -> String {
+    let mut text = String::new();
+    let mut line_length = 0;
+
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+        text.push_str(token);
+        line_length += token.len();
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::{parse_game, AnnotationColor, ArrowAnnotation, SquareHighlight};
+    use shakmaty::Square;
+
+    const ANNOTATED_GAME: &str = "[Event \"Annotations\"]
+
+{[%cal Ge2e4] Main idea} 1. e4 e5 {[%csl Rf7]} 2. Nf3 ({[%cal Yf1c4]} 2. Bc4) 2... Nc6 *
+";
+
+    fn arrow(from: Square, to: Square, color: AnnotationColor) -> ArrowAnnotation {
+        ArrowAnnotation { from, to, color }
+    }
+
+    #[test]
+    fn annotations_before_a_move_stay_on_the_position_before_it() {
+        let game = parse_game(ANNOTATED_GAME).unwrap();
+        let written = write_game(&game);
+        let reparsed = parse_game(&written).unwrap();
+
+        assert!(written.contains("{[%cal Ge2e4]} {Main idea} 1. e4"));
+        for tree in &[&game.tree, &reparsed.tree] {
+            let main_line = tree.main_line();
+            let (e4, e5) = (main_line[0], main_line[1]);
+
+            assert_eq!(
+                tree.node(GameTree::ROOT).annotations.arrows,
+                vec![arrow(Square::E2, Square::E4, AnnotationColor::Green)]
+            );
+            assert!(tree.node(e4).annotations.is_empty());
+            assert_eq!(
+                tree.node(e5).annotations.squares,
+                vec![SquareHighlight {
+                    square: Square::F7,
+                    color: AnnotationColor::Red,
+                }]
+            );
+            assert_eq!(
+                tree.node(e5).annotations.arrows,
+                vec![arrow(Square::F1, Square::C4, AnnotationColor::Yellow)]
+            );
+            assert_eq!(tree.node(e4).comment_before, Some("Main idea".to_string()));
+        }
+    }
+
+    #[test]
+    fn writes_variations_comments_and_nags_back() {
+        let text = "[Event \"Round trip\"]
+
+1. d4 $1 {Solid} (1. e4 e5) 1... d5 2. c4 $5 *
+";
+        let written = write_game(&parse_game(text).unwrap());
+
+        assert_eq!(
+            written,
+            "[Event \"Round trip\"]\n\n1. d4 $1 {Solid} ( 1. e4 e5 ) 1... d5 2. c4 $5 *\n"
+        );
+    }
+}
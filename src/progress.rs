@@ -0,0 +1,6 @@
+mod attempts_database;
+mod history_report;
+mod statistics;
+pub use attempts_database::*;
+pub use history_report::*;
+pub use statistics::*;
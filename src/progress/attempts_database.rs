@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+use crate::pgn::{position_key, NodeId};
+use crate::reviser::Verdict;
+use crate::storage::{
+    append_json_line, check_version, data_file_path, load_json_lines, StorageError,
+};
+
+const ATTEMPTS_DATABASE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attempt {
+    pub file: String,
+    pub game: usize,
+    pub game_title: String,
+    pub eco: Option<String>,
+    pub node: NodeId,
+    pub fen: String,
+    pub timestamp: u64,
+    pub guessed_move: String,
+    pub expected_move: String,
+    pub verdict: Verdict,
+    pub time_taken_ms: u64,
+}
+
+impl Attempt {
+    pub fn is_success(&self) -> bool {
+        self.verdict != Verdict::Wrong
+    }
+
+    pub fn is_in_game(&self, file: &str, game: usize) -> bool {
+        self.file == file && self.game == game
+    }
+
+    // Move counters are ignored, as for the schedule cards.
+    pub fn is_at_position(&self, fen: &str) -> bool {
+        position_key(&self.fen) == position_key(fen)
+    }
+}
+
+// The file starts with a header line, followed by one line per attempt.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum AttemptsLine<A> {
+    Header { version: u32 },
+    Attempt(A),
+}
+
+#[derive(Debug, Default)]
+pub struct AttemptsDatabase {
+    attempts: Vec<Attempt>,
+}
+
+impl AttemptsDatabase {
+    pub fn default_path() -> Option<PathBuf> {
+        data_file_path("attempts.jsonl")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        let mut database = AttemptsDatabase::default();
+        for line in load_json_lines::<AttemptsLine<Attempt>>(path)?.unwrap_or_default() {
+            match line {
+                AttemptsLine::Header { version } => {
+                    check_version(path, version, ATTEMPTS_DATABASE_VERSION)?
+                }
+                AttemptsLine::Attempt(attempt) => database.attempts.push(attempt),
+            }
+        }
+
+        Ok(database)
+    }
+
+    /// Only the new attempt is written, at the end of the file.
+    pub fn add(&mut self, attempt: Attempt, path: Option<&Path>) -> Result<(), StorageError> {
+        let saved = match path {
+            Some(path) => append_attempt(path, &attempt),
+            None => Ok(()),
+        };
+        self.attempts.push(attempt);
+
+        saved
+    }
+
+    pub fn attempts(&self) -> &[Attempt] {
+        &self.attempts
+    }
+
+    pub fn for_game<'a>(&'a self, file: &'a str, game: usize) -> impl Iterator<Item = &'a Attempt> {
+        self.attempts
+            .iter()
+            .filter(move |attempt| attempt.is_in_game(file, game))
+    }
+
+    pub fn for_position<'a>(&'a self, fen: &'a str) -> impl Iterator<Item = &'a Attempt> {
+        self.attempts
+            .iter()
+            .filter(move |attempt| attempt.is_at_position(fen))
+    }
+}
+
+fn append_attempt(path: &Path, attempt: &Attempt) -> Result<(), StorageError> {
+    if !path.exists() {
+        append_json_line(
+            path,
+            &AttemptsLine::<&Attempt>::Header {
+                version: ATTEMPTS_DATABASE_VERSION,
+            },
+        )?;
+    }
+    append_json_line(path, &AttemptsLine::Attempt(attempt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "chess-pgn-reviser-{}-{}.jsonl",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn attempt(guessed_move: &str) -> Attempt {
+        Attempt {
+            file: "openings.pgn".to_string(),
+            game: 2,
+            game_title: "White - Black".to_string(),
+            eco: Some("C50".to_string()),
+            node: 3,
+            fen: "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".to_string(),
+            timestamp: 1_600_000_000,
+            guessed_move: guessed_move.to_string(),
+            expected_move: "g1f3".to_string(),
+            verdict: if guessed_move == "g1f3" {
+                Verdict::MainLine
+            } else {
+                Verdict::Wrong
+            },
+            time_taken_ms: 2500,
+        }
+    }
+
+    #[test]
+    fn appends_one_line_per_attempt() {
+        let path = temporary_path("append");
+        let mut database = AttemptsDatabase::default();
+        database.add(attempt("f1c4"), Some(&path)).unwrap();
+        database.add(attempt("g1f3"), Some(&path)).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        assert_eq!(content.lines().next(), Some("{\"version\":1}"));
+
+        let loaded = AttemptsDatabase::load(&path).unwrap();
+        assert_eq!(loaded.attempts(), database.attempts());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keeps_attempts_in_memory_without_a_file() {
+        let mut database = AttemptsDatabase::default();
+        database.add(attempt("g1f3"), None).unwrap();
+
+        assert_eq!(database.attempts(), &[attempt("g1f3")]);
+    }
+
+    #[test]
+    fn refuses_an_unknown_version() {
+        let path = temporary_path("version");
+        fs::write(&path, "{\"version\":2}\n").unwrap();
+
+        let loaded = AttemptsDatabase::load(&path);
+        fs::remove_file(&path).unwrap();
+        match loaded {
+            Err(StorageError::UnsupportedVersion { version, .. }) => assert_eq!(version, 2),
+            other => panic!("unexpected load result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use shakmaty::san::SanPlus;
+use shakmaty::uci::Uci;
+
+use super::attempts_database::{Attempt, AttemptsDatabase};
+use crate::pgn::position_from_fen;
+use crate::reviser::Verdict;
+
+const LISTED_ATTEMPTS: usize = 5;
+
+pub fn game_history_text(database: &AttemptsDatabase, file: &str, game: usize, now: u64) -> String {
+    let attempts = database.for_game(file, game).collect::<Vec<_>>();
+    history_text("This game", &attempts, now)
+}
+
+pub fn position_history_text(database: &AttemptsDatabase, fen: &str, now: u64) -> String {
+    let attempts = database.for_position(fen).collect::<Vec<_>>();
+    history_text("This position", &attempts, now)
+}
+
+fn history_text(title: &str, attempts: &[&Attempt], now: u64) -> String {
+    if attempts.is_empty() {
+        return format!("{}: never revised", title);
+    }
+
+    let successes = attempts
+        .iter()
+        .filter(|attempt| attempt.is_success())
+        .count();
+    let mut lines = vec![format!(
+        "{}: {} attempts, {}% correct",
+        title,
+        attempts.len(),
+        successes * 100 / attempts.len()
+    )];
+    lines.extend(
+        attempts
+            .iter()
+            .rev()
+            .take(LISTED_ATTEMPTS)
+            .map(|attempt| attempt_line(attempt, now)),
+    );
+
+    lines.join("\n")
+}
+
+fn attempt_line(attempt: &Attempt, now: u64) -> String {
+    let guessed = move_san(&attempt.fen, &attempt.guessed_move);
+    let verdict = match attempt.verdict {
+        Verdict::MainLine => "main line".to_string(),
+        Verdict::Variation(_) => "variation".to_string(),
+        Verdict::Wrong => format!(
+            "wrong, expected {}",
+            move_san(&attempt.fen, &attempt.expected_move)
+        ),
+    };
+
+    format!(
+        "  {}: {} ({}) in {:.1} s",
+        elapsed_text(now.saturating_sub(attempt.timestamp)),
+        guessed,
+        verdict,
+        attempt.time_taken_ms as f64 / 1000.0
+    )
+}
+
+pub fn move_san(fen: &str, uci: &str) -> String {
+    let san = position_from_fen(fen).and_then(|position| {
+        let legal_move = Uci::from_ascii(uci.as_bytes())
+            .ok()?
+            .to_move(&position)
+            .ok()?;
+        Some(SanPlus::from_move(position, &legal_move).to_string())
+    });
+
+    san.unwrap_or_else(|| uci.to_string())
+}
+
+fn elapsed_text(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const AFTER_E5: &str = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    const NOW: u64 = 1_600_000_000;
+
+    fn attempt(game: usize, fen: &str, guessed_move: &str, seconds_ago: u64) -> Attempt {
+        Attempt {
+            file: "openings.pgn".to_string(),
+            game,
+            game_title: "White - Black".to_string(),
+            eco: None,
+            node: 3,
+            fen: fen.to_string(),
+            timestamp: NOW - seconds_ago,
+            guessed_move: guessed_move.to_string(),
+            expected_move: "g1f3".to_string(),
+            verdict: if guessed_move == "g1f3" {
+                Verdict::MainLine
+            } else {
+                Verdict::Wrong
+            },
+            time_taken_ms: 2500,
+        }
+    }
+
+    fn database(attempts: Vec<Attempt>) -> AttemptsDatabase {
+        let mut database = AttemptsDatabase::default();
+        for attempt in attempts {
+            database.add(attempt, None).unwrap();
+        }
+        database
+    }
+
+    #[test]
+    fn tells_a_game_was_never_revised() {
+        let database = database(vec![attempt(1, AFTER_E5, "g1f3", 0)]);
+
+        assert_eq!(
+            game_history_text(&database, "openings.pgn", 0, NOW),
+            "This game: never revised"
+        );
+    }
+
+    #[test]
+    fn lists_the_latest_attempts_of_a_game_first() {
+        let database = database(vec![
+            attempt(0, AFTER_E5, "f1c4", 7200),
+            attempt(0, AFTER_E5, "g1f3", 30),
+            attempt(1, AFTER_E5, "g1f3", 0),
+        ]);
+
+        assert_eq!(
+            game_history_text(&database, "openings.pgn", 0, NOW),
+            "This game: 2 attempts, 50% correct\n  \
+             just now: Nf3 (main line) in 2.5 s\n  \
+             2 h ago: Bc4 (wrong, expected Nf3) in 2.5 s"
+        );
+    }
+
+    #[test]
+    fn lists_only_the_latest_attempts_of_a_position() {
+        let database = database(
+            (0..7)
+                .map(|index| {
+                    // Move counters do not change the position.
+                    let fen = AFTER_E5.replace("0 2", &format!("0 {}", index + 2));
+                    attempt(index, &fen, "g1f3", 60 * index as u64)
+                })
+                .collect(),
+        );
+
+        let text = position_history_text(&database, AFTER_E5, NOW);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1 + LISTED_ATTEMPTS);
+        assert_eq!(lines[0], "This position: 7 attempts, 100% correct");
+        assert_eq!(lines[1], "  6 min ago: Nf3 (main line) in 2.5 s");
+    }
+
+    #[test]
+    fn writes_the_elapsed_time() {
+        assert_eq!(elapsed_text(59), "just now");
+        assert_eq!(elapsed_text(60), "1 min ago");
+        assert_eq!(elapsed_text(3599), "59 min ago");
+        assert_eq!(elapsed_text(3600), "1 h ago");
+        assert_eq!(elapsed_text(3 * 86400 + 5), "3 days ago");
+    }
+
+    #[test]
+    fn writes_moves_in_san_when_legal() {
+        assert_eq!(move_san(START, "g1f3"), "Nf3");
+        assert_eq!(move_san(START, "e2e5"), "e2e5");
+        assert_eq!(move_san("not a fen", "e2e4"), "e2e4");
+    }
+}
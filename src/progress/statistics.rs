@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use super::attempts_database::{Attempt, AttemptsDatabase};
+use crate::pgn::{position_key, NodeId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accuracy {
+    pub label: String,
+    pub attempts: usize,
+    pub successes: usize,
+}
+
+impl Accuracy {
+    fn new(label: String) -> Self {
+        Accuracy {
+            label,
+            attempts: 0,
+            successes: 0,
+        }
+    }
+
+    fn add(&mut self, attempt: &Attempt) {
+        self.attempts += 1;
+        if attempt.is_success() {
+            self.successes += 1;
+        }
+    }
+
+    pub fn success_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.successes as f64 / self.attempts as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionStatistics {
+    pub accuracy: Accuracy,
+    pub file: String,
+    pub game: usize,
+    pub node: NodeId,
+    pub fen: String,
+    pub expected_move: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Statistics {
+    pub per_game: Vec<Accuracy>,
+    pub per_eco: Vec<Accuracy>,
+    pub worst_positions: Vec<PositionStatistics>,
+}
+
+impl Statistics {
+    pub fn compute(database: &AttemptsDatabase, worst_positions_count: usize) -> Self {
+        let attempts = database.attempts();
+
+        Statistics {
+            per_game: accuracies_by(attempts, |attempt| {
+                (
+                    format!("{}#{}", attempt.file, attempt.game),
+                    attempt.game_title.clone(),
+                )
+            }),
+            per_eco: accuracies_by(attempts, |attempt| {
+                let eco = attempt.eco.clone().unwrap_or_else(|| "?".to_string());
+                (eco.clone(), eco)
+            }),
+            worst_positions: worst_positions(attempts, worst_positions_count),
+        }
+    }
+}
+
+// Groups the attempts by key, keeping the first seen order, and labels each group.
+fn accuracies_by<F>(attempts: &[Attempt], key_and_label: F) -> Vec<Accuracy>
+where
+    F: Fn(&Attempt) -> (String, String),
+{
+    let mut indexes = HashMap::new();
+    let mut accuracies: Vec<Accuracy> = vec![];
+
+    for attempt in attempts {
+        let (key, label) = key_and_label(attempt);
+        let index = *indexes.entry(key).or_insert_with(|| {
+            accuracies.push(Accuracy::new(label));
+            accuracies.len() - 1
+        });
+        accuracies[index].add(attempt);
+    }
+
+    accuracies
+}
+
+fn worst_positions(attempts: &[Attempt], count: usize) -> Vec<PositionStatistics> {
+    let mut positions: HashMap<String, PositionStatistics> = HashMap::new();
+
+    for attempt in attempts {
+        let key = format!("{} {}", position_key(&attempt.fen), attempt.expected_move);
+        let position = positions.entry(key).or_insert_with(|| PositionStatistics {
+            accuracy: Accuracy::new(attempt.game_title.clone()),
+            file: attempt.file.clone(),
+            game: attempt.game,
+            node: attempt.node,
+            fen: attempt.fen.clone(),
+            expected_move: attempt.expected_move.clone(),
+        });
+        // The latest attempt tells where to find the position again.
+        position.file = attempt.file.clone();
+        position.game = attempt.game;
+        position.node = attempt.node;
+        position.accuracy.label = attempt.game_title.clone();
+        position.accuracy.add(attempt);
+    }
+
+    let mut positions = positions
+        .into_iter()
+        .map(|(_, position)| position)
+        .filter(|position| position.accuracy.successes < position.accuracy.attempts)
+        .collect::<Vec<_>>();
+    positions.sort_by(|first, second| {
+        first
+            .accuracy
+            .success_rate()
+            .partial_cmp(&second.accuracy.success_rate())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(second.accuracy.attempts.cmp(&first.accuracy.attempts))
+    });
+    positions.truncate(count);
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviser::Verdict;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const AFTER_E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    const AFTER_E5: &str = "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+    const AFTER_NF3: &str = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+
+    fn attempt(
+        game: usize,
+        eco: Option<&str>,
+        fen: &str,
+        expected_move: &str,
+        success: bool,
+    ) -> Attempt {
+        Attempt {
+            file: "openings.pgn".to_string(),
+            game,
+            game_title: format!("Game {}", game),
+            eco: eco.map(str::to_string),
+            node: game * 10,
+            fen: fen.to_string(),
+            timestamp: 1_600_000_000,
+            guessed_move: if success { expected_move } else { "a2a3" }.to_string(),
+            expected_move: expected_move.to_string(),
+            verdict: if success {
+                Verdict::MainLine
+            } else {
+                Verdict::Wrong
+            },
+            time_taken_ms: 1000,
+        }
+    }
+
+    fn database() -> AttemptsDatabase {
+        let mut database = AttemptsDatabase::default();
+        let attempts = vec![
+            attempt(0, Some("C50"), AFTER_E5, "g1f3", true),
+            // The same position, reached with other move counters.
+            attempt(
+                0,
+                Some("C50"),
+                &AFTER_E5.replace("0 2", "4 7"),
+                "g1f3",
+                false,
+            ),
+            attempt(1, None, START, "e2e4", false),
+            attempt(1, None, START, "e2e4", false),
+            attempt(2, Some("C50"), AFTER_E4, "e7e5", true),
+            attempt(2, Some("C50"), AFTER_E4, "e7e5", false),
+            attempt(2, Some("C50"), AFTER_E4, "e7e5", true),
+            attempt(2, Some("C50"), AFTER_E4, "e7e5", false),
+            attempt(2, Some("C50"), AFTER_NF3, "b8c6", true),
+        ];
+        for attempt in attempts {
+            database.add(attempt, None).unwrap();
+        }
+        database
+    }
+
+    fn counts(accuracies: &[Accuracy]) -> Vec<(&str, usize, usize)> {
+        accuracies
+            .iter()
+            .map(|accuracy| {
+                (
+                    accuracy.label.as_str(),
+                    accuracy.attempts,
+                    accuracy.successes,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn counts_the_accuracy_per_game() {
+        let statistics = Statistics::compute(&database(), 10);
+
+        assert_eq!(
+            counts(&statistics.per_game),
+            vec![("Game 0", 2, 1), ("Game 1", 2, 0), ("Game 2", 5, 3)]
+        );
+        assert!((statistics.per_game[2].success_rate() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn counts_the_accuracy_per_eco() {
+        let statistics = Statistics::compute(&database(), 10);
+
+        assert_eq!(
+            counts(&statistics.per_eco),
+            vec![("C50", 7, 4), ("?", 2, 0)]
+        );
+    }
+
+    #[test]
+    fn lists_the_worst_positions_first() {
+        let statistics = Statistics::compute(&database(), 10);
+
+        let positions = statistics
+            .worst_positions
+            .iter()
+            .map(|position| {
+                (
+                    position_key(&position.fen),
+                    position.accuracy.attempts,
+                    position.accuracy.successes,
+                )
+            })
+            .collect::<Vec<_>>();
+        // Equal rates are ordered by the number of attempts, and the never
+        // failed position after Nf3 is left out.
+        assert_eq!(
+            positions,
+            vec![
+                (position_key(START), 2, 0),
+                (position_key(AFTER_E4), 4, 2),
+                (position_key(AFTER_E5), 2, 1),
+            ]
+        );
+        assert_eq!(statistics.worst_positions[0].expected_move, "e2e4");
+        assert_eq!(statistics.worst_positions[0].node, 10);
+    }
+
+    #[test]
+    fn keeps_the_requested_number_of_worst_positions() {
+        let statistics = Statistics::compute(&database(), 2);
+
+        assert_eq!(statistics.worst_positions.len(), 2);
+        assert_eq!(statistics.worst_positions[1].fen, AFTER_E4);
+    }
+
+    #[test]
+    fn has_no_statistics_without_attempts() {
+        let statistics = Statistics::compute(&AttemptsDatabase::default(), 10);
+
+        assert_eq!(statistics, Statistics::default());
+        assert!(Accuracy::new("empty".to_string()).success_rate().abs() < 1e-9);
+    }
+}
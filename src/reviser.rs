@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::{Chess, Color, Move, Position};
+
+use crate::pgn::{GameTree, NodeId};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum PlayerKind {
+    Human,
+    Computer,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Verdict {
+    MainLine,
+    // Index of the matched variation among the node children (the main line being 0).
+    Variation(usize),
+    Wrong,
+}
+
+pub struct ReviserSession {
+    tree: GameTree,
+    white_player: PlayerKind,
+    black_player: PlayerKind,
+    cursor: NodeId,
+    accepted_child: Option<NodeId>,
+    preferred_line: Vec<NodeId>,
+    in_book: bool,
+}
+
+impl ReviserSession {
+    pub fn new(tree: GameTree, white_player: PlayerKind, black_player: PlayerKind) -> Self {
+        ReviserSession {
+            tree,
+            white_player,
+            black_player,
+            cursor: GameTree::ROOT,
+            accepted_child: None,
+            preferred_line: vec![],
+            in_book: true,
+        }
+    }
+
+    pub fn tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    pub fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, node: NodeId) {
+        self.accepted_child = None;
+        self.cursor = node;
+        self.in_book = true;
+    }
+
+    /// Once a move missing from the tree is played, the cursor no longer follows the board.
+    pub fn leave_book(&mut self) {
+        self.accepted_child = None;
+        self.in_book = false;
+    }
+
+    pub fn is_in_book(&self) -> bool {
+        self.in_book
+    }
+
+    pub fn set_preferred_line(&mut self, leaf: Option<NodeId>) {
+        self.preferred_line = leaf.map_or(vec![], |leaf| self.tree.path_to(leaf));
+    }
+
+    // Index, among the cursor children, of the one leading along the preferred line.
+    pub fn preferred_child_index(&self) -> Option<usize> {
+        self.tree
+            .node(self.cursor)
+            .children
+            .iter()
+            .position(|child| self.preferred_line.contains(child))
+    }
+
+    pub fn current_position(&self) -> &Chess {
+        &self.tree.node(self.cursor).position
+    }
+
+    pub fn player_kind(&self, color: Color) -> PlayerKind {
+        match color {
+            Color::White => self.white_player,
+            Color::Black => self.black_player,
+        }
+    }
+
+    pub fn player_to_move(&self) -> PlayerKind {
+        self.player_kind(self.current_position().turn())
+    }
+
+    pub fn is_line_finished(&self) -> bool {
+        self.tree.node(self.cursor).children.is_empty()
+    }
+
+    pub fn expected_moves(&self) -> Vec<Move> {
+        self.tree
+            .node(self.cursor)
+            .children
+            .iter()
+            .filter_map(|child| self.tree.node(*child).move_played.clone())
+            .collect()
+    }
+
+    /// None when it is not for the human side to move.
+    pub fn submit_move(&mut self, played_move: &Move) -> Option<Verdict> {
+        self.accepted_child = None;
+        if self.player_to_move() != PlayerKind::Human {
+            return None;
+        }
+
+        let matching_child = self
+            .tree
+            .node(self.cursor)
+            .children
+            .iter()
+            .position(|child| self.tree.node(*child).move_played.as_ref() == Some(played_move));
+
+        Some(match matching_child {
+            Some(child_index) => {
+                self.accepted_child = Some(self.tree.node(self.cursor).children[child_index]);
+                if child_index == 0 {
+                    Verdict::MainLine
+                } else {
+                    Verdict::Variation(child_index)
+                }
+            }
+            None => Verdict::Wrong,
+        })
+    }
+
+    pub fn advance_to(&mut self, child_index: usize) -> Option<NodeId> {
+        let next = *self.tree.node(self.cursor).children.get(child_index)?;
+        self.accepted_child = None;
+        self.cursor = next;
+
+        Some(next)
+    }
+
+    pub fn follow_move(&mut self, played_move: &Move) -> Option<NodeId> {
+        let child_index = self
+            .expected_moves()
+            .iter()
+            .position(|expected_move| expected_move == played_move)?;
+
+        self.advance_to(child_index)
+    }
+
+    pub fn advance(&mut self) -> Option<NodeId> {
+        let next = self
+            .accepted_child
+            .take()
+            .or_else(|| self.tree.main_line_child(self.cursor))?;
+        self.cursor = next;
+
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_game;
+    use shakmaty::san::San;
+
+    // Node ids: 1. e4 (1) e5 (2) ( 1... c5 (3) 2. Nf3 (4) ) 2. Nf3 (5) Nc6 (6)
+    const REPERTOIRE: &str = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 *";
+
+    // The computer plays White, the human Black.
+    fn session() -> ReviserSession {
+        let tree = parse_game(REPERTOIRE).unwrap().tree;
+        ReviserSession::new(tree, PlayerKind::Computer, PlayerKind::Human)
+    }
+
+    fn legal_move(session: &ReviserSession, san: &str) -> Move {
+        San::from_ascii(san.as_bytes())
+            .unwrap()
+            .to_move(session.current_position())
+            .unwrap()
+    }
+
+    #[test]
+    fn lists_the_expected_moves_main_line_first() {
+        let mut session = session();
+        let e4 = legal_move(&session, "e4");
+        assert_eq!(session.expected_moves(), vec![e4]);
+
+        session.set_cursor(1);
+        let expected = vec![legal_move(&session, "e5"), legal_move(&session, "c5")];
+        assert_eq!(session.expected_moves(), expected);
+    }
+
+    #[test]
+    fn judges_the_human_moves() {
+        let mut session = session();
+        session.set_cursor(1);
+
+        let e5 = legal_move(&session, "e5");
+        assert_eq!(session.submit_move(&e5), Some(Verdict::MainLine));
+        let c5 = legal_move(&session, "c5");
+        assert_eq!(session.submit_move(&c5), Some(Verdict::Variation(1)));
+        let d5 = legal_move(&session, "d5");
+        assert_eq!(session.submit_move(&d5), Some(Verdict::Wrong));
+        assert_eq!(session.cursor(), 1);
+    }
+
+    #[test]
+    fn does_not_judge_a_move_on_the_computer_turn() {
+        let mut session = session();
+        let e4 = legal_move(&session, "e4");
+
+        assert_eq!(session.player_to_move(), PlayerKind::Computer);
+        assert_eq!(session.submit_move(&e4), None);
+        assert_eq!(session.cursor(), GameTree::ROOT);
+    }
+
+    #[test]
+    fn advances_along_the_accepted_move() {
+        let mut session = session();
+        session.set_cursor(1);
+        let c5 = legal_move(&session, "c5");
+        session.submit_move(&c5);
+
+        assert_eq!(session.advance(), Some(3));
+        assert_eq!(session.player_to_move(), PlayerKind::Computer);
+        assert_eq!(session.advance(), Some(4));
+        assert!(session.is_line_finished());
+        assert_eq!(session.advance(), None);
+    }
+
+    #[test]
+    fn the_computer_plays_the_chosen_continuation() {
+        let mut session = session();
+
+        assert_eq!(session.advance_to(1), None);
+        assert_eq!(session.advance_to(0), Some(1));
+        assert_eq!(session.advance_to(1), Some(3));
+        assert_eq!(session.cursor(), 3);
+    }
+
+    #[test]
+    fn prefers_the_child_on_the_preferred_line() {
+        let mut session = session();
+        session.set_preferred_line(Some(4));
+        assert_eq!(session.preferred_child_index(), Some(0));
+
+        session.set_cursor(1);
+        assert_eq!(session.preferred_child_index(), Some(1));
+
+        session.set_preferred_line(None);
+        assert_eq!(session.preferred_child_index(), None);
+    }
+
+    #[test]
+    fn follows_only_the_moves_of_the_tree() {
+        let mut session = session();
+        session.set_cursor(1);
+
+        let d5 = legal_move(&session, "d5");
+        assert_eq!(session.follow_move(&d5), None);
+        assert_eq!(session.cursor(), 1);
+
+        let c5 = legal_move(&session, "c5");
+        assert_eq!(session.follow_move(&c5), Some(3));
+        assert_eq!(session.cursor(), 3);
+    }
+
+    #[test]
+    fn comes_back_in_book_when_the_cursor_is_set() {
+        let mut session = session();
+        assert!(session.is_in_book());
+
+        session.leave_book();
+        assert!(!session.is_in_book());
+
+        session.set_cursor(2);
+        assert!(session.is_in_book());
+    }
+}
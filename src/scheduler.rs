@@ -0,0 +1,4 @@
+mod card;
+mod schedule;
+pub use card::*;
+pub use schedule::*;
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const INITIAL_EASE_FACTOR: f64 = 2.5;
+const MINIMUM_EASE_FACTOR: f64 = 1.3;
+const PASSED_QUALITY: f64 = 4.0;
+const FAILED_QUALITY: f64 = 1.0;
+
+/// SM-2 state of a single position to guess.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    pub repetitions: u32,
+    pub interval_days: u32,
+    pub ease_factor: f64,
+    pub due: u64,
+    pub lapses: u32,
+}
+
+impl Card {
+    pub fn new(now: u64) -> Self {
+        Card {
+            repetitions: 0,
+            interval_days: 0,
+            ease_factor: INITIAL_EASE_FACTOR,
+            due: now,
+            lapses: 0,
+        }
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.due <= now
+    }
+
+    pub fn review(&mut self, passed: bool, now: u64) {
+        let quality = if passed {
+            PASSED_QUALITY
+        } else {
+            FAILED_QUALITY
+        };
+
+        if passed {
+            self.repetitions += 1;
+            self.interval_days = match self.repetitions {
+                1 => 1,
+                2 => 6,
+                _ => (self.interval_days as f64 * self.ease_factor).round() as u32,
+            };
+        } else {
+            self.repetitions = 0;
+            self.interval_days = 1;
+            self.lapses += 1;
+        }
+
+        let ease_factor =
+            self.ease_factor + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02);
+        self.ease_factor = ease_factor.max(MINIMUM_EASE_FACTOR);
+        self.due = now + self.interval_days as u64 * SECONDS_PER_DAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_600_000_000;
+
+    #[test]
+    fn passed_reviews_grow_the_interval() {
+        let mut card = Card::new(NOW);
+
+        card.review(true, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (1, 1));
+        assert_eq!(card.due, NOW + SECONDS_PER_DAY);
+
+        card.review(true, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (2, 6));
+
+        card.review(true, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (3, 15));
+        assert_eq!(card.due, NOW + 15 * SECONDS_PER_DAY);
+        assert!((card.ease_factor - INITIAL_EASE_FACTOR).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lapse_resets_the_repetitions() {
+        let mut card = Card::new(NOW);
+        card.review(true, NOW);
+        card.review(true, NOW);
+
+        card.review(false, NOW);
+        assert_eq!(card.repetitions, 0);
+        assert_eq!(card.interval_days, 1);
+        assert_eq!(card.lapses, 1);
+        assert!(card.ease_factor < INITIAL_EASE_FACTOR);
+    }
+
+    #[test]
+    fn the_ease_factor_never_goes_below_its_floor() {
+        let mut card = Card::new(NOW);
+        for _ in 0..5 {
+            card.review(false, NOW);
+        }
+
+        assert!((card.ease_factor - MINIMUM_EASE_FACTOR).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_card_is_due_once_its_interval_is_over() {
+        let mut card = Card::new(NOW);
+        assert!(card.is_due(NOW));
+
+        card.review(true, NOW);
+        assert!(!card.is_due(NOW));
+        assert!(!card.is_due(NOW + SECONDS_PER_DAY - 1));
+        assert!(card.is_due(NOW + SECONDS_PER_DAY));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use shakmaty::fen::fen;
+use shakmaty::uci::Uci;
+use shakmaty::{Color, Position};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::card::Card;
+use crate::pgn::{position_key, GameTree, NodeId};
+use crate::reviser::Verdict;
+use crate::storage::{check_version, data_file_path, load_json, save_json, StorageError};
+
+const SCHEDULE_VERSION: u32 = 1;
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    version: u32,
+    cards: HashMap<String, Card>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            version: SCHEDULE_VERSION,
+            cards: HashMap::new(),
+        }
+    }
+}
+
+impl Schedule {
+    pub fn default_path() -> Option<PathBuf> {
+        data_file_path("schedule.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, StorageError> {
+        match load_json::<Schedule>(path)? {
+            Some(schedule) => {
+                check_version(path, schedule.version, SCHEDULE_VERSION)?;
+                Ok(schedule)
+            }
+            None => Ok(Schedule::default()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), StorageError> {
+        save_json(path, self)
+    }
+
+    pub fn card(&self, key: &str) -> Option<&Card> {
+        self.cards.get(key)
+    }
+
+    pub fn record(&mut self, key: String, passed: bool, now: u64) {
+        self.cards
+            .entry(key)
+            .or_insert_with(|| Card::new(now))
+            .review(passed, now);
+    }
+
+    /// A found move reviews its own card, a wrong guess fails the cards of every expected move.
+    pub fn record_guess(&mut self, tree: &GameTree, node: NodeId, verdict: Verdict, now: u64) {
+        let children = &tree.node(node).children;
+        let reviewed = match verdict {
+            Verdict::MainLine => children.iter().take(1).collect::<Vec<_>>(),
+            Verdict::Variation(child_index) => children.iter().skip(child_index).take(1).collect(),
+            Verdict::Wrong => children.iter().collect(),
+        };
+
+        for child in reviewed {
+            if let Some(key) = card_key_for_move(tree, *child) {
+                self.record(key, verdict != Verdict::Wrong, now);
+            }
+        }
+    }
+
+    // Cards never reviewed are due.
+    pub fn is_due(&self, key: &str, now: u64) -> bool {
+        self.card(key).map_or(true, |card| card.is_due(now))
+    }
+
+    /// Leaf of the line holding the most due cards for the human sides, if any is due.
+    pub fn most_due_line<F>(&self, tree: &GameTree, is_human: F, now: u64) -> Option<NodeId>
+    where
+        F: Fn(Color) -> bool,
+    {
+        let due_cards = |node: NodeId| {
+            tree.path_to(node)
+                .into_iter()
+                .filter(|id| {
+                    tree.node(*id)
+                        .parent
+                        .map_or(false, |parent| is_human(tree.node(parent).position.turn()))
+                })
+                .filter_map(|id| card_key_for_move(tree, id))
+                .filter(|key| self.is_due(key, now))
+                .count()
+        };
+
+        (0..tree.nodes_count())
+            .filter(|id| tree.node(*id).children.is_empty())
+            .map(|leaf| (due_cards(leaf), leaf))
+            .filter(|(due_count, _)| *due_count > 0)
+            .max_by_key(|(due_count, leaf)| (*due_count, std::cmp::Reverse(*leaf)))
+            .map(|(_, leaf)| leaf)
+    }
+}
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Stable key of a card: the move counters are left out of the fen so that
+/// the same position reached at another move number shares its card.
+pub fn card_key(position_fen: &str, expected_uci: &str) -> String {
+    let position = position_key(position_fen);
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in position
+        .bytes()
+        .chain(b" ".iter().copied())
+        .chain(expected_uci.bytes())
+    {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// Key of the card for the move leading to the node, from its parent position.
+pub fn card_key_for_move(tree: &GameTree, node: NodeId) -> Option<String> {
+    let parent = tree.node(node).parent?;
+    let expected_move = tree.node(node).move_played.as_ref()?;
+
+    Some(card_key(
+        &fen(&tree.node(parent).position),
+        &Uci::from_standard(expected_move).to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_game;
+
+    const NOW: u64 = 1_600_000_000;
+    // Node ids: 1. e4 (1) e5 (2) ( 1... c5 (3) 2. Nf3 (4) ) 2. Nf3 (5)
+    const REPERTOIRE: &str = "1. e4 e5 (1... c5 2. Nf3) 2. Nf3 *";
+
+    fn repertoire() -> GameTree {
+        parse_game(REPERTOIRE).unwrap().tree
+    }
+
+    fn only_black(color: Color) -> bool {
+        color == Color::Black
+    }
+
+    #[test]
+    fn card_keys_ignore_the_move_counters() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let later = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 8 5";
+
+        assert_eq!(card_key(start, "e2e4"), card_key(later, "e2e4"));
+        assert_ne!(card_key(start, "e2e4"), card_key(start, "d2d4"));
+    }
+
+    #[test]
+    fn a_found_variation_reviews_its_own_card() {
+        let tree = repertoire();
+        let mut schedule = Schedule::default();
+        schedule.record_guess(&tree, 1, Verdict::Variation(1), NOW);
+
+        let c5_card = schedule.card(&card_key_for_move(&tree, 3).unwrap());
+        assert_eq!(c5_card.map(|card| card.repetitions), Some(1));
+        assert!(schedule
+            .card(&card_key_for_move(&tree, 2).unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn a_wrong_guess_fails_every_expected_move() {
+        let tree = repertoire();
+        let mut schedule = Schedule::default();
+        schedule.record_guess(&tree, 1, Verdict::Wrong, NOW);
+
+        for node in &[2, 3] {
+            let card = schedule.card(&card_key_for_move(&tree, *node).unwrap());
+            assert_eq!(card.map(|card| card.lapses), Some(1));
+        }
+    }
+
+    #[test]
+    fn picks_the_line_with_the_most_due_cards() {
+        let tree = repertoire();
+        let mut schedule = Schedule::default();
+        schedule.record_guess(&tree, 1, Verdict::Variation(1), NOW);
+
+        assert_eq!(schedule.most_due_line(&tree, only_black, NOW), Some(5));
+    }
+
+    #[test]
+    fn ties_go_to_the_first_line_of_the_file() {
+        let tree = repertoire();
+        let schedule = Schedule::default();
+
+        assert_eq!(schedule.most_due_line(&tree, only_black, NOW), Some(4));
+        assert_eq!(schedule.most_due_line(&tree, |_| true, NOW), Some(4));
+    }
+
+    #[test]
+    fn no_line_is_picked_when_nothing_is_due() {
+        let tree = repertoire();
+        let mut schedule = Schedule::default();
+        schedule.record_guess(&tree, 1, Verdict::MainLine, NOW);
+        schedule.record_guess(&tree, 1, Verdict::Variation(1), NOW);
+
+        assert_eq!(schedule.most_due_line(&tree, only_black, NOW), None);
+        assert_eq!(
+            schedule.most_due_line(&tree, only_black, NOW + 24 * 60 * 60),
+            Some(4)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use failure::Fail;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Fail)]
+pub enum StorageError {
+    #[fail(display = "Could not access {}: {}", path, cause)]
+    Io { path: String, cause: String },
+    #[fail(display = "Malformed file {}: {}", path, cause)]
+    Format { path: String, cause: String },
+    #[fail(display = "Unsupported version {} of file {}", version, path)]
+    UnsupportedVersion { path: String, version: u32 },
+}
+
+pub fn data_file_path(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("chess-pgn-reviser").join(file_name))
+}
+
+/// Gives None when the file does not exist yet.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, StorageError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path).map_err(|err| StorageError::Io {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    })?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|err| StorageError::Format {
+            path: path.display().to_string(),
+            cause: err.to_string(),
+        })
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    let io_error = |err: std::io::Error| StorageError::Io {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let content = serde_json::to_string_pretty(value).map_err(|err| StorageError::Format {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    })?;
+
+    fs::write(path, content).map_err(io_error)
+}
+
+/// Gives None when the file does not exist yet, its non empty lines otherwise.
+pub fn load_json_lines<T: DeserializeOwned>(path: &Path) -> Result<Option<Vec<T>>, StorageError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(path).map_err(|err| StorageError::Io {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    })?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| StorageError::Format {
+                path: path.display().to_string(),
+                cause: format!("line {}: {}", index + 1, err),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+pub fn append_json_line<T: Serialize>(path: &Path, value: &T) -> Result<(), StorageError> {
+    let io_error = |err: std::io::Error| StorageError::Io {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    let mut line = serde_json::to_string(value).map_err(|err| StorageError::Format {
+        path: path.display().to_string(),
+        cause: err.to_string(),
+    })?;
+    line.push('\n');
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(io_error)
+}
+
+pub fn check_version(path: &Path, version: u32, supported: u32) -> Result<(), StorageError> {
+    if version == supported {
+        Ok(())
+    } else {
+        Err(StorageError::UnsupportedVersion {
+            path: path.display().to_string(),
+            version,
+        })
+    }
+}
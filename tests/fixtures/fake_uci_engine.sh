@@ -0,0 +1,25 @@
+#!/bin/sh
+# Scripted stand-in for a UCI engine: every command is echoed back as an info string
+# before the canned answer, so that tests can check what the client sent.
+while read -r command; do
+    echo "info string received $command"
+    case "$command" in
+        uci)
+            echo "id name Fake Engine"
+            echo "id author Test Suite"
+            echo "option name Skill Level type spin default 20 min 0 max 20"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        go*)
+            echo "info depth 12 seldepth 18 multipv 1 score cp 34 nodes 12000 nps 600000 time 20 pv e2e4 e7e5 g1f3"
+            echo "info depth 12 multipv 2 score mate -3 pv d2d4 d7d5"
+            echo "bestmove e2e4 ponder e7e5"
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done